@@ -1,32 +1,88 @@
+const SHADE_MODE_FLAT_DERIVATIVE: u32 = 1u;
+const FOG_MODE_LINEAR: u32 = 1u;
+const FOG_MODE_EXP: u32 = 2u;
+const BLEND_MODE_PREMULTIPLIED: u32 = 1u;
+const COLOR_MODE_VERTEX: u32 = 1u;
+const COLOR_MODE_PRODUCT: u32 = 2u;
+const MAX_CLIP_PLANES: u32 = 4u;
+
 struct Uniforms {
     view_proj: mat4x4<f32>,
+    inv_view_proj: mat4x4<f32>,
+    fog_color: vec4<f32>,
+    // Direction the light travels
+    light_direction: vec4<f32>,
+    // Color in rgb, intensity in a
+    light_color: vec4<f32>,
+    shade_mode: u32,
+    fog_mode: u32,
+    fog_start: f32,
+    fog_end: f32,
+    fog_density: f32,
+    clip_plane_count: u32,
+    blend_mode: u32,
+    point_light_count: u32,
+    color_mode: u32,
+    // Scale applied to edges drawn from solid instances so they win the
+    // depth test against their own faces
+    outline_scale: f32,
+    outline_color: vec4<f32>,
+    // Plane equations, fragments with dot(xyz, p) + w < 0 are discarded
+    clip_planes: array<vec4<f32>, MAX_CLIP_PLANES>,
+}
+
+struct PointLight {
+    // World position in xyz, range in w
+    position_range: vec4<f32>,
+    // Color in rgb, intensity in a
+    color_intensity: vec4<f32>,
 }
 
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) color: vec3<f32>,
     @location(2) normal: vec3<f32>,
+    @location(3) uv: vec2<f32>,
 }
 
 struct Instance {
-    @location(3) model_matrix_x: vec4<f32>,
-    @location(4) model_matrix_y: vec4<f32>,
-    @location(5) model_matrix_z: vec4<f32>,
-    @location(6) model_matrix_t: vec4<f32>,
-    @location(7) color: vec4<f32>,
+    @location(4) model_matrix_x: vec4<f32>,
+    @location(5) model_matrix_y: vec4<f32>,
+    @location(6) model_matrix_z: vec4<f32>,
+    @location(7) model_matrix_t: vec4<f32>,
+    @location(8) color: vec4<f32>,
+    // Atlas offset (xy) and scale (zw) applied to UVs before sampling
+    @location(9) uv_region: vec4<f32>,
 }
 
 struct VertexOutput {
-    @builtin(position) clip_position: vec4<f32>,
+    // Invariant so a depth prepass and the color pass agree exactly
+    @builtin(position) @invariant clip_position: vec4<f32>,
     @location(0) color: vec4<f32>,
     @location(1) world_normal: vec3<f32>,
+    @location(2) world_position: vec3<f32>,
+    @location(3) uv: vec2<f32>,
+    @location(4) view_depth: f32,
+    @location(5) uv_region: vec4<f32>,
+    @location(6) vertex_color: vec3<f32>,
 }
 
 @group(0) @binding(0)
 var<uniform> uniforms: Uniforms;
 
-@vertex
-fn vs_main(input: VertexInput, instance: Instance) -> VertexOutput {
+// Swapped for a fixed-size uniform array where storage buffers are
+// unavailable, see `create_default_shader`
+@group(0) @binding(1)
+var<storage, read> point_lights: array<PointLight>;
+
+// Per-mesh texture source. Meshes without one are bound to a 1x1 white
+// texture so untextured geometry is unaffected.
+@group(1) @binding(0)
+var base_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var base_sampler: sampler;
+
+fn transform_vertex(input: VertexInput, instance: Instance) -> VertexOutput {
     let model_matrix = mat4x4<f32>(
         instance.model_matrix_x,
         instance.model_matrix_y,
@@ -34,22 +90,168 @@ fn vs_main(input: VertexInput, instance: Instance) -> VertexOutput {
         instance.model_matrix_t,
     );
 
+    let world_position = model_matrix * vec4<f32>(input.position, 1.0);
+
     var output: VertexOutput;
-    output.clip_position = uniforms.view_proj * model_matrix * vec4<f32>(input.position, 1.0);
+    output.clip_position = uniforms.view_proj * world_position;
     output.color = instance.color;
     output.world_normal = normalize((model_matrix * vec4<f32>(input.normal, 0.0)).xyz);
+    output.world_position = world_position.xyz;
+    output.uv = input.uv;
+    output.uv_region = instance.uv_region;
+    output.vertex_color = input.color;
+    // For a perspective projection clip w is the view-space depth
+    output.view_depth = output.clip_position.w;
+    return output;
+}
+
+@vertex
+fn vs_main(input: VertexInput, instance: Instance) -> VertexOutput {
+    return transform_vertex(input, instance);
+}
+
+// Edges drawn straight from the solid instances in the renderer's outline
+// style, see `outline_scale`
+@vertex
+fn edge_vs_main(input: VertexInput, instance: Instance) -> VertexOutput {
+    var inflated = input;
+    inflated.position = input.position * uniforms.outline_scale;
+    var output = transform_vertex(inflated, instance);
+    output.color = uniforms.outline_color;
     return output;
 }
 
+fn apply_fog(color: vec4<f32>, view_depth: f32) -> vec4<f32> {
+    var fog = 0.0;
+    if uniforms.fog_mode == FOG_MODE_LINEAR {
+        fog = (view_depth - uniforms.fog_start) / max(uniforms.fog_end - uniforms.fog_start, 1e-5);
+    } else if uniforms.fog_mode == FOG_MODE_EXP {
+        fog = 1.0 - exp(-uniforms.fog_density * max(view_depth - uniforms.fog_start, 0.0));
+    }
+    return vec4<f32>(mix(color.rgb, uniforms.fog_color.rgb, clamp(fog, 0.0, 1.0)), color.a);
+}
+
+fn is_clipped(world_position: vec3<f32>) -> bool {
+    for (var i = 0u; i < min(uniforms.clip_plane_count, MAX_CLIP_PLANES); i++) {
+        let plane = uniforms.clip_planes[i];
+        if dot(plane.xyz, world_position) + plane.w < 0.0 {
+            return true;
+        }
+    }
+    return false;
+}
+
+// Diffuse light from all point lights, fading smoothly to zero at their range
+fn point_light_diffuse(world_position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    var total = vec3<f32>(0.0);
+    for (var i = 0u; i < uniforms.point_light_count; i++) {
+        let light = point_lights[i];
+        let to_light = light.position_range.xyz - world_position;
+        let distance = length(to_light);
+        let range = max(light.position_range.w, 1e-5);
+        let window = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+        let attenuation = window * window / (distance * distance + 1.0);
+        let lambert = max(dot(normal, to_light / max(distance, 1e-5)), 0.0);
+        total += light.color_intensity.rgb * light.color_intensity.a * lambert * attenuation;
+    }
+    return total;
+}
+
+// Face normal from the screen-space derivatives of the world position. The
+// sign of dpdy depends on the backend's framebuffer orientation (GL/WebGL flip
+// it), so the result is oriented to agree with the interpolated normal.
+fn flat_normal(world_position: vec3<f32>, world_normal: vec3<f32>) -> vec3<f32> {
+    let n = normalize(cross(dpdy(world_position), dpdx(world_position)));
+    return select(n, -n, dot(n, world_normal) < 0.0);
+}
+
 // Fragment shader for solid render pass
 @fragment
 fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
-    let ambient_color = vec4<f32>(vec3<f32>(0.5), 1.0);
-    return vec4<f32>(ambient_color * input.color);
+    let smooth_normal = normalize(input.world_normal);
+    let normal = select(
+        smooth_normal,
+        flat_normal(input.world_position, smooth_normal),
+        uniforms.shade_mode == SHADE_MODE_FLAT_DERIVATIVE,
+    );
+
+    let uv = input.uv_region.xy + input.uv * input.uv_region.zw;
+    // Alpha always comes from the instance, so translucency works in every
+    // color mode
+    var surface_color = input.color;
+    if uniforms.color_mode == COLOR_MODE_VERTEX {
+        surface_color = vec4<f32>(input.vertex_color, input.color.a);
+    } else if uniforms.color_mode == COLOR_MODE_PRODUCT {
+        surface_color = vec4<f32>(input.vertex_color * input.color.rgb, input.color.a);
+    }
+    let base_color = surface_color * textureSample(base_texture, base_sampler, uv);
+
+    let ambient = 0.5;
+    let lambert = max(dot(normal, -uniforms.light_direction.xyz), 0.0);
+    let diffuse = uniforms.light_color.rgb * uniforms.light_color.a * lambert
+        + point_light_diffuse(input.world_position, normal);
+    let color = vec4<f32>(base_color.rgb * (ambient + diffuse), base_color.a);
+    // After the derivatives and texture sample, which need uniform control flow
+    if is_clipped(input.world_position) {
+        discard;
+    }
+    let fogged = apply_fog(color, input.view_depth);
+    if uniforms.blend_mode == BLEND_MODE_PREMULTIPLIED {
+        return vec4<f32>(fogged.rgb * fogged.a, fogged.a);
+    }
+    return fogged;
 }
 
 // Fragment shader for outline render pass
 @fragment
 fn outline_fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
-    return input.color;
+    if is_clipped(input.world_position) {
+        discard;
+    }
+    return apply_fog(input.color, input.view_depth);
+}
+
+// Fragment shader for the edge-detect outline prepass: face normal and view
+// depth, read back by edge_detect.wgsl
+@fragment
+fn normal_depth_fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let normal = flat_normal(input.world_position, normalize(input.world_normal));
+    if is_clipped(input.world_position) {
+        discard;
+    }
+    return vec4<f32>(normal, input.view_depth);
+}
+
+// Fragment shader for depth-only passes, honoring clip planes
+@fragment
+fn depth_fs_main(input: VertexOutput) {
+    if is_clipped(input.world_position) {
+        discard;
+    }
+}
+
+struct PickOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) @interpolate(flat) id: u32,
+}
+
+// Object-id pass for `Renderer::pick`, the id is carried in the instance
+// color's red channel, exact up to `MAX_PICKABLE_COMMANDS`
+@vertex
+fn pick_vs_main(input: VertexInput, instance: Instance) -> PickOutput {
+    let vertex = transform_vertex(input, instance);
+    var output: PickOutput;
+    output.clip_position = vertex.clip_position;
+    output.world_position = vertex.world_position;
+    output.id = u32(instance.color.r);
+    return output;
+}
+
+@fragment
+fn pick_fs_main(input: PickOutput) -> @location(0) u32 {
+    if is_clipped(input.world_position) {
+        discard;
+    }
+    return input.id;
 }
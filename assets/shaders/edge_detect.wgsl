@@ -0,0 +1,59 @@
+// Screen-space outlines from a normal + view depth target (xyz normal, w
+// depth), drawn over the shaded scene with alpha blending
+
+// Depth gradient relative to the pixel's own depth that counts as an edge
+const DEPTH_THRESHOLD: f32 = 0.1;
+// Normal gradient magnitude that counts as a crease
+const NORMAL_THRESHOLD: f32 = 1.0;
+
+// Edge color, set when the pipeline is built
+override OUTLINE_RED: f32 = 1.0;
+override OUTLINE_GREEN: f32 = 1.0;
+override OUTLINE_BLUE: f32 = 1.0;
+override OUTLINE_ALPHA: f32 = 1.0;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var normal_depth: texture_2d<f32>;
+// Unused, the bind group layout is shared with the blit passes
+@group(0) @binding(1)
+var normal_depth_sampler: sampler;
+
+// Single triangle covering the whole viewport, no vertex buffer needed
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let max_coord = vec2<i32>(textureDimensions(normal_depth)) - 1;
+    let center = vec2<i32>(in.clip_position.xy);
+
+    // 3x3 Sobel over depth and normals
+    var depth_gradient = vec2<f32>(0.0);
+    var normal_gx = vec3<f32>(0.0);
+    var normal_gy = vec3<f32>(0.0);
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let coord = clamp(center + vec2<i32>(x, y), vec2<i32>(0), max_coord);
+            let sample = textureLoad(normal_depth, coord, 0);
+            let weight = vec2<f32>(f32(x * (2 - abs(y))), f32(y * (2 - abs(x))));
+            depth_gradient += sample.w * weight;
+            normal_gx += sample.xyz * weight.x;
+            normal_gy += sample.xyz * weight.y;
+        }
+    }
+
+    let center_depth = textureLoad(normal_depth, clamp(center, vec2<i32>(0), max_coord), 0).w;
+    let depth_edge = length(depth_gradient) / max(center_depth, 1e-3);
+    let normal_edge = sqrt(dot(normal_gx, normal_gx) + dot(normal_gy, normal_gy));
+    let edge = max(step(DEPTH_THRESHOLD, depth_edge), step(NORMAL_THRESHOLD, normal_edge));
+    return vec4<f32>(OUTLINE_RED, OUTLINE_GREEN, OUTLINE_BLUE, OUTLINE_ALPHA * edge);
+}
@@ -0,0 +1,146 @@
+//! egui panel over the scene, toggling wireframe rendering with a checkbox.
+//! Run with `cargo run --example egui --features egui`.
+
+use raiden_rs::{
+    camera::PanOrbitCamera,
+    commands::DrawCommandBuilder,
+    egui_overlay::EguiOverlay,
+    mesh::MeshType,
+    renderer::{RenderMode, Renderer},
+};
+use std::sync::Arc;
+use winit::{
+    application::ApplicationHandler,
+    event::WindowEvent,
+    event_loop::{ActiveEventLoop, EventLoop},
+    window::Window,
+};
+
+struct State {
+    window: Arc<Window>,
+    renderer: Renderer,
+    camera: PanOrbitCamera,
+    overlay: EguiOverlay,
+    egui_input: egui_winit::State,
+    wireframe: bool,
+}
+
+impl State {
+    fn new(window: Arc<Window>) -> anyhow::Result<Self> {
+        let renderer = pollster::block_on(Renderer::from_winit_window(window.clone()))?;
+        let overlay = EguiOverlay::for_renderer(&renderer);
+        let egui_input = egui_winit::State::new(
+            overlay.context.clone(),
+            egui::ViewportId::ROOT,
+            &window,
+            Some(window.scale_factor() as f32),
+            None,
+            None,
+        );
+        Ok(Self {
+            window,
+            renderer,
+            camera: PanOrbitCamera::default(),
+            overlay,
+            egui_input,
+            wireframe: false,
+        })
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        if let Some(surface_size) = self.renderer.resize(width, height) {
+            self.camera.update_aspect(surface_size);
+            self.renderer.update_uniforms(&self.camera);
+        }
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let input = self.egui_input.take_egui_input(&self.window);
+        let mut wireframe = self.wireframe;
+        let platform_output = self.overlay.run(input, |ctx| {
+            egui::Window::new("raiden-rs").show(ctx, |ui| {
+                ui.checkbox(&mut wireframe, "Wireframe");
+            });
+        });
+        self.egui_input
+            .handle_platform_output(&self.window, platform_output);
+        if wireframe != self.wireframe {
+            self.wireframe = wireframe;
+            self.renderer.set_render_mode(if wireframe {
+                RenderMode::Wireframe
+            } else {
+                RenderMode::Solid
+            });
+        }
+
+        self.renderer.commands.push(
+            DrawCommandBuilder::new(MeshType::Cube)
+                .with_scale(0.1)
+                .build(),
+        );
+        let size_in_pixels = [
+            self.renderer.surface_config.width,
+            self.renderer.surface_config.height,
+        ];
+        let overlay = &mut self.overlay;
+        self.renderer
+            .render_with_overlay(|device, queue, encoder, view| {
+                overlay.paint(device, queue, encoder, view, size_in_pixels);
+            })
+    }
+}
+
+#[derive(Default)]
+struct App {
+    state: Option<State>,
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let window = event_loop
+            .create_window(Window::default_attributes().with_title("raiden-rs egui"))
+            .unwrap();
+        self.state = Some(State::new(Arc::new(window)).unwrap());
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        _window_id: winit::window::WindowId,
+        event: WindowEvent,
+    ) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        if state
+            .egui_input
+            .on_window_event(&state.window, &event)
+            .consumed
+        {
+            return;
+        }
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(size) => state.resize(size.width, size.height),
+            WindowEvent::RedrawRequested => {
+                match state.render() {
+                    Ok(_) => {}
+                    Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                        let size = state.window.inner_size();
+                        state.resize(size.width, size.height);
+                    }
+                    Err(e) => log::error!("Unable to render {}", e),
+                }
+                state.window.request_redraw();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    env_logger::init();
+    let event_loop = EventLoop::new()?;
+    event_loop.run_app(&mut App::default())?;
+    Ok(())
+}
@@ -7,7 +7,12 @@ use winit::{
     window::Window,
 };
 
-use raiden_rs::{camera::PanOrbitCamera, commands::DrawCommandBuilder, mesh::MeshType};
+use raiden_rs::{
+    camera::{Camera, PanOrbitCamera},
+    commands::{DrawCommand, DrawCommandBuilder},
+    mesh::MeshType,
+    renderer::{Corner, RenderMode, ShadeMode},
+};
 use std::collections::BTreeMap;
 use std::sync::Arc;
 
@@ -22,6 +27,95 @@ pub struct MouseState {
     pub position: glam::Vec2,
     pub position_needs_update: bool,
     pub touches: BTreeMap<u64, PhysicalPosition<f64>>,
+    /// Latest cursor position while it is over the window
+    pub hover: Option<glam::Vec2>,
+    pub hover_needs_update: bool,
+}
+
+impl MouseState {
+    pub fn any_button_pressed(&self) -> bool {
+        self.button_left || self.button_middle || self.button_right
+    }
+}
+
+/// Scales vertical drag distance (pixels) into zoom steps for `DragAction::Zoom`
+const DRAG_ZOOM_FACTOR: f32 = 0.1;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DragAction {
+    Orbit,
+    Pan,
+    Zoom,
+}
+
+/// Camera action performed while dragging with each mouse button. Conventions
+/// differ between tools (Blender, Maya, CAD packages), so each button can be
+/// remapped or disabled.
+#[derive(Debug, Copy, Clone)]
+pub struct ButtonMapping {
+    pub left: Option<DragAction>,
+    pub middle: Option<DragAction>,
+    pub right: Option<DragAction>,
+}
+
+impl Default for ButtonMapping {
+    fn default() -> Self {
+        Self {
+            left: Some(DragAction::Orbit),
+            middle: Some(DragAction::Pan),
+            right: Some(DragAction::Pan),
+        }
+    }
+}
+
+impl ButtonMapping {
+    /// Actions bound to the currently held buttons, without duplicates
+    pub fn drag_actions(&self, mouse_state: &MouseState) -> Vec<DragAction> {
+        let mut actions = Vec::new();
+        for (pressed, action) in [
+            (mouse_state.button_left, self.left),
+            (mouse_state.button_middle, self.middle),
+            (mouse_state.button_right, self.right),
+        ] {
+            if let Some(action) = action.filter(|_| pressed)
+                && !actions.contains(&action)
+            {
+                actions.push(action);
+            }
+        }
+        actions
+    }
+}
+
+/// Window and scene setup for [`run_with`]
+pub struct AppOptions {
+    pub title: String,
+    /// Initial window size in physical pixels, or the platform default
+    pub inner_size: Option<winit::dpi::PhysicalSize<u32>>,
+    /// Commands drawn every frame
+    pub initial_scene: Vec<DrawCommand>,
+    pub initial_camera: PanOrbitCamera,
+}
+
+impl Default for AppOptions {
+    fn default() -> Self {
+        Self {
+            title: "raiden-rs".to_string(),
+            inner_size: None,
+            initial_scene: demo_scene(),
+            initial_camera: PanOrbitCamera::default(),
+        }
+    }
+}
+
+fn demo_scene() -> Vec<DrawCommand> {
+    vec![
+        DrawCommandBuilder::new(MeshType::Cube)
+            .with_position([0.0, 0.0, 0.0].into())
+            .with_scale(0.1)
+            .with_color_u8(255, 255, 255, 255)
+            .build(),
+    ]
 }
 
 pub struct State {
@@ -30,14 +124,21 @@ pub struct State {
     window: Arc<Window>,
     pub renderer: raiden_rs::renderer::Renderer,
     pub mouse_state: MouseState,
+    pub button_mapping: ButtonMapping,
     pub camera: PanOrbitCamera,
+    pub scene: Vec<DrawCommand>,
+    pub flat_shading: bool,
+    /// Outline the object under the cursor while no button is held
+    pub hover_highlight: bool,
 }
 
 impl State {
-    pub async fn new(window: Arc<Window>) -> anyhow::Result<Self> {
+    pub async fn new(
+        window: Arc<Window>,
+        scene: Vec<DrawCommand>,
+        camera: PanOrbitCamera,
+    ) -> anyhow::Result<Self> {
         let mut renderer = raiden_rs::renderer::Renderer::from_winit_window(window.clone()).await?;
-        let camera = PanOrbitCamera::default();
-        renderer.enable_outlines = true;
         renderer.update_uniforms(&camera);
 
         Ok(Self {
@@ -46,80 +147,128 @@ impl State {
             window,
             renderer,
             mouse_state: MouseState::default(),
+            button_mapping: ButtonMapping::default(),
             camera,
+            scene,
+            flat_shading: false,
+            hover_highlight: true,
         })
     }
 
+    /// Add the scene as retained commands once the surface is ready, so
+    /// hover highlights can refer to them by handle
     pub fn set_render_commands(&mut self) {
-        if !self.is_surface_configured {
+        if !self.is_surface_configured || self.is_scene_initialized {
             return;
         }
-        self.renderer.commands.push(
-            DrawCommandBuilder::new(MeshType::Cube)
-                .with_position([0.0, 0.0, 0.0].into())
-                .with_scale(0.1)
-                .with_color_u8(255, 255, 255, 255)
-                .build(),
-        );
-        self.renderer.commands.push(
-            DrawCommandBuilder::new(MeshType::Tetrahedron)
-                .with_position([4.0, 0.0, 0.0].into())
-                .with_scale(0.1)
-                .with_color_u8(255, 0, 0, 255)
-                .build(),
-        );
-        self.renderer.commands.push(
-            DrawCommandBuilder::new(MeshType::Sphere)
-                .with_position([0.0, 4.0, 0.0].into())
-                .with_scale(0.1)
-                .with_color_u8(0, 255, 0, 255)
-                .build(),
-        );
-        self.renderer.commands.push(
-            DrawCommandBuilder::new(MeshType::Tetrahedron)
-                .with_position([0.0, 0.0, 4.0].into())
-                .with_scale(0.1)
-                .with_color_u8(0, 0, 255, 255)
-                .build(),
-        );
+        for command in &self.scene {
+            self.renderer.add_command(command.clone());
+        }
         self.is_scene_initialized = true;
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
-        if width > 0 && height > 0 {
-            // Needed for firefox
-            const MAX_DIM: u32 = 2048;
-            let scale = if width > MAX_DIM || height > MAX_DIM {
-                MAX_DIM as f32 / width.max(height) as f32
-            } else {
-                1.0
-            };
-
-            let window_size = glam::uvec2(
-                (width as f32 * scale) as u32,
-                (height as f32 * scale) as u32,
-            );
-            log::debug!("Window Size: {}", window_size);
-            self.camera.update_aspect(window_size);
-            self.renderer.resize(window_size, &self.camera);
+        if let Some(surface_size) = self.renderer.resize(width, height) {
+            log::debug!("Surface Size: {}", surface_size);
+            self.camera.update_aspect(surface_size);
+            self.renderer.update_uniforms(&self.camera);
             self.is_surface_configured = true;
         }
     }
 
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
         self.window.request_redraw();
-        if !self.is_surface_configured {
+        if !self.is_surface_configured || self.renderer.paused {
             return Ok(());
         }
         self.set_render_commands();
+        if self.camera.auto_orbit.is_some() || self.camera.is_animating() {
+            self.camera.tick(std::time::Duration::from_secs_f32(
+                self.renderer.frame_delta,
+            ));
+            self.renderer.update_uniforms(&self.camera);
+            self.mouse_state.hover_needs_update = true;
+        }
+        self.update_hover();
+        self.renderer.draw_axis_gizmo(Corner::BottomLeft, 96);
         self.renderer.render()?;
         Ok(())
     }
 
+    /// Pick the object under the cursor, at most once per frame
+    fn update_hover(&mut self) {
+        if !self.mouse_state.hover_needs_update {
+            return;
+        }
+        self.mouse_state.hover_needs_update = false;
+        self.renderer.highlighted.clear();
+        if !self.hover_highlight || self.mouse_state.any_button_pressed() {
+            return;
+        }
+        let Some(cursor) = self.mouse_state.hover else {
+            return;
+        };
+        let size = self.window.inner_size();
+        let (origin, dir) = self
+            .camera
+            .screen_ray(cursor, glam::uvec2(size.width, size.height));
+        if let Some((handle, _)) = self.renderer.pick_precise(origin, dir) {
+            self.renderer.highlighted.insert(handle);
+        }
+    }
+
     pub fn handle_key(&mut self, event_loop: &ActiveEventLoop, code: KeyCode, is_pressed: bool) {
         match (code, is_pressed) {
             (KeyCode::Escape, true) => event_loop.exit(),
-            (KeyCode::KeyO, true) => self.renderer.enable_outlines = !self.renderer.enable_outlines,
+            (KeyCode::KeyO, true) => {
+                self.renderer
+                    .set_render_mode(if self.renderer.render_mode == RenderMode::Solid {
+                        RenderMode::SolidWithOutline
+                    } else {
+                        RenderMode::Solid
+                    })
+            }
+            (KeyCode::KeyP, true) => self.renderer.paused = !self.renderer.paused,
+            (KeyCode::KeyH, true) => {
+                self.hover_highlight = !self.hover_highlight;
+                self.mouse_state.hover_needs_update = true;
+            }
+            (KeyCode::KeyF, true) => {
+                self.flat_shading = !self.flat_shading;
+                self.renderer.set_shade_mode(if self.flat_shading {
+                    ShadeMode::FlatDerivative
+                } else {
+                    ShadeMode::Smooth
+                });
+            }
+            (
+                KeyCode::ArrowLeft | KeyCode::ArrowRight | KeyCode::ArrowUp | KeyCode::ArrowDown,
+                true,
+            ) => {
+                const STEP: f32 = 0.1;
+                match code {
+                    KeyCode::ArrowLeft => self.camera.orbit_by(STEP, 0.0),
+                    KeyCode::ArrowRight => self.camera.orbit_by(-STEP, 0.0),
+                    KeyCode::ArrowUp => self.camera.orbit_by(0.0, -STEP),
+                    _ => self.camera.orbit_by(0.0, STEP),
+                }
+                self.renderer.update_uniforms(&self.camera);
+            }
+            (KeyCode::KeyT, true) => {
+                const TURNTABLE_SPEED: f32 = 0.5;
+                self.camera.auto_orbit = match self.camera.auto_orbit {
+                    Some(_) => None,
+                    None => Some(TURNTABLE_SPEED),
+                };
+            }
+            (KeyCode::KeyV, true) => {
+                let vsync = self.renderer.surface_config.present_mode == wgpu::PresentMode::Fifo;
+                self.renderer.set_present_mode(if vsync {
+                    wgpu::PresentMode::Mailbox
+                } else {
+                    wgpu::PresentMode::Fifo
+                });
+            }
             _ => {}
         }
     }
@@ -129,14 +278,29 @@ pub struct App {
     #[cfg(target_arch = "wasm32")]
     proxy: Option<winit::event_loop::EventLoopProxy<State>>,
     state: Option<State>,
+    /// Taken when the window is first created
+    options: Option<AppOptions>,
 }
 
 impl App {
+    #[allow(clippy::new_without_default)]
     pub fn new(#[cfg(target_arch = "wasm32")] event_loop: &EventLoop<State>) -> Self {
+        Self::with_options(
+            #[cfg(target_arch = "wasm32")]
+            event_loop,
+            AppOptions::default(),
+        )
+    }
+
+    pub fn with_options(
+        #[cfg(target_arch = "wasm32")] event_loop: &EventLoop<State>,
+        options: AppOptions,
+    ) -> Self {
         #[cfg(target_arch = "wasm32")]
         let proxy = Some(event_loop.create_proxy());
         Self {
             state: None,
+            options: Some(options),
             #[cfg(target_arch = "wasm32")]
             proxy,
         }
@@ -145,8 +309,15 @@ impl App {
 
 impl ApplicationHandler<State> for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        // Only the first resume creates the window
+        let Some(options) = self.options.take() else {
+            return;
+        };
         #[allow(unused_mut)]
-        let mut window_attributes = Window::default_attributes();
+        let mut window_attributes = Window::default_attributes().with_title(options.title);
+        if let Some(size) = options.inner_size {
+            window_attributes = window_attributes.with_inner_size(size);
+        }
 
         #[cfg(target_arch = "wasm32")]
         {
@@ -169,7 +340,14 @@ impl ApplicationHandler<State> for App {
 
         #[cfg(not(target_arch = "wasm32"))]
         {
-            self.state = Some(pollster::block_on(State::new(window)).unwrap());
+            self.state = Some(
+                pollster::block_on(State::new(
+                    window,
+                    options.initial_scene,
+                    options.initial_camera,
+                ))
+                .unwrap(),
+            );
         }
 
         #[cfg(target_arch = "wasm32")]
@@ -178,7 +356,11 @@ impl ApplicationHandler<State> for App {
                 wasm_bindgen_futures::spawn_local(async move {
                     assert!(
                         proxy
-                            .send_event(State::new(window).await.expect("Unable to create canvas"))
+                            .send_event(
+                                State::new(window, options.initial_scene, options.initial_camera)
+                                    .await
+                                    .expect("Unable to create canvas")
+                            )
                             .is_ok()
                     )
                 });
@@ -248,6 +430,7 @@ impl ApplicationHandler<State> for App {
                     MouseScrollDelta::PixelDelta(delta) => app_state.camera.zoom(delta.y as f32),
                 }
                 app_state.renderer.update_uniforms(&app_state.camera);
+                app_state.mouse_state.hover_needs_update = true;
             }
             WindowEvent::Touch(Touch {
                 id,
@@ -256,9 +439,8 @@ impl ApplicationHandler<State> for App {
                 ..
             }) => match phase {
                 TouchPhase::Started => {
-                    if app_state.mouse_state.touches.insert(id, location).is_none()
-                        && app_state.mouse_state.touches.len() == 1
-                    {
+                    let is_new_touch = app_state.mouse_state.touches.insert(id, location).is_none();
+                    if is_new_touch && app_state.mouse_state.touches.len() == 1 {
                         app_state.mouse_state.position.x = location.x as f32;
                         app_state.mouse_state.position.y = location.y as f32;
                     }
@@ -319,33 +501,36 @@ impl ApplicationHandler<State> for App {
                 }
                 _ => {}
             },
+            WindowEvent::CursorLeft { .. } => {
+                app_state.mouse_state.hover = None;
+                app_state.mouse_state.hover_needs_update = true;
+            }
             WindowEvent::CursorMoved { position, .. } => {
+                app_state.mouse_state.hover =
+                    Some(glam::vec2(position.x as f32, position.y as f32));
+                app_state.mouse_state.hover_needs_update = true;
                 if app_state.mouse_state.position_needs_update {
                     app_state.mouse_state.position.x = position.x as f32;
                     app_state.mouse_state.position.y = position.y as f32;
                     app_state.mouse_state.position_needs_update = false;
                 } else {
-                    if app_state.mouse_state.button_left {
-                        let mouse_delta = [
-                            position.x as f32 - app_state.mouse_state.position.x,
-                            position.y as f32 - app_state.mouse_state.position.y,
-                        ]
-                        .into();
-                        app_state.camera.orbit(mouse_delta);
-                        app_state.renderer.update_uniforms(&app_state.camera);
-                        app_state.mouse_state.position.x = position.x as f32;
-                        app_state.mouse_state.position.y = position.y as f32;
+                    let position = glam::vec2(position.x as f32, position.y as f32);
+                    let mouse_delta = position - app_state.mouse_state.position;
+                    let actions = app_state
+                        .button_mapping
+                        .drag_actions(&app_state.mouse_state);
+                    for action in &actions {
+                        match action {
+                            DragAction::Orbit => app_state.camera.orbit(mouse_delta),
+                            DragAction::Pan => app_state.camera.pan(mouse_delta),
+                            DragAction::Zoom => {
+                                app_state.camera.zoom(-mouse_delta.y * DRAG_ZOOM_FACTOR)
+                            }
+                        }
                     }
-                    if app_state.mouse_state.button_right {
-                        let mouse_delta = [
-                            position.x as f32 - app_state.mouse_state.position.x,
-                            position.y as f32 - app_state.mouse_state.position.y,
-                        ]
-                        .into();
-                        app_state.camera.pan(mouse_delta);
+                    if !actions.is_empty() {
                         app_state.renderer.update_uniforms(&app_state.camera);
-                        app_state.mouse_state.position.x = position.x as f32;
-                        app_state.mouse_state.position.y = position.y as f32;
+                        app_state.mouse_state.position = position;
                     }
                 }
             }
@@ -355,6 +540,10 @@ impl ApplicationHandler<State> for App {
 }
 
 pub fn run() -> anyhow::Result<()> {
+    run_with(AppOptions::default())
+}
+
+pub fn run_with(options: AppOptions) -> anyhow::Result<()> {
     #[cfg(not(target_arch = "wasm32"))]
     {
         env_logger::init();
@@ -367,9 +556,10 @@ pub fn run() -> anyhow::Result<()> {
 
     let event_loop = EventLoop::with_user_event().build()?;
 
-    let mut app = App::new(
+    let mut app = App::with_options(
         #[cfg(target_arch = "wasm32")]
         &event_loop,
+        options,
     );
     event_loop.run_app(&mut app)?;
 
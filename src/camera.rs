@@ -6,6 +6,26 @@ pub trait Camera {
     fn proj_matrix(&self) -> &glam::Mat4;
     fn proj_matrix_mut(&mut self) -> &mut glam::Mat4;
 
+    /// Maps clip space back to world space, e.g. for rays through screen
+    /// points
+    fn view_proj_inverse(&self) -> glam::Mat4 {
+        (*self.proj_matrix() * *self.view_matrix()).inverse()
+    }
+
+    /// World-space ray through `mouse`, in pixels from the top-left of a
+    /// `viewport`-sized surface, as an origin on the near plane and a unit
+    /// direction
+    fn screen_ray(&self, mouse: glam::Vec2, viewport: glam::UVec2) -> (glam::Vec3, glam::Vec3) {
+        let ndc = glam::vec2(
+            mouse.x / viewport.x.max(1) as f32 * 2.0 - 1.0,
+            1.0 - mouse.y / viewport.y.max(1) as f32 * 2.0,
+        );
+        let inv_view_proj = self.view_proj_inverse();
+        let near = inv_view_proj.project_point3(ndc.extend(0.0));
+        let far = inv_view_proj.project_point3(ndc.extend(1.0));
+        (near, (far - near).normalize())
+    }
+
     fn affine(&self) -> glam::Affine3A {
         glam::Affine3A::from_mat4(*self.view_matrix())
     }
@@ -31,6 +51,40 @@ pub trait Camera {
     }
 }
 
+/// The six planes bounding a view-projection's visible volume, normals
+/// pointing inward
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Frustum {
+    planes: [glam::Vec4; 6],
+}
+
+impl Frustum {
+    /// Planes of `view_proj`, which maps depth to [0, 1] like wgpu's
+    /// projections
+    pub fn from_view_proj(view_proj: glam::Mat4) -> Self {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|i| view_proj.row(i));
+        let planes = [w + x, w - x, w + y, w - y, z, w - z]
+            .map(|plane| plane / plane.truncate().length().max(f32::EPSILON));
+        Self { planes }
+    }
+
+    /// Whether any part of the sphere may be inside
+    pub fn intersects_sphere(&self, center: glam::Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(center) + plane.w >= -radius)
+    }
+}
+
+/// Transition started by [`PanOrbitCamera::animate_to`]
+#[derive(Debug, Copy, Clone)]
+struct CameraAnimation {
+    from: (glam::Vec3, f32, glam::Quat),
+    to: (glam::Vec3, f32, glam::Quat),
+    elapsed: std::time::Duration,
+    duration: std::time::Duration,
+}
+
 #[derive(Debug)]
 pub struct PanOrbitCamera {
     pub target: glam::Vec3,
@@ -41,6 +95,17 @@ pub struct PanOrbitCamera {
     pub mouse_speed: f32,
     pub zoom_speed: f32,
     pub pan_speed: f32,
+    pub pan_plane: Option<glam::Vec3>,
+    /// Unit axis orbits yaw about and `reset_roll` levels to, +Z by default.
+    /// Change with [`Self::set_world_up`].
+    pub world_up: glam::Vec3,
+    /// Stop orbits just short of looking straight up or down, like Blender
+    /// or Maya, instead of letting the view tumble over the pole
+    pub clamp_pitch: bool,
+    /// Turntable speed in radians per second about `world_up`, applied by
+    /// [`Self::tick`] while no transition is running
+    pub auto_orbit: Option<f32>,
+    animation: Option<CameraAnimation>,
     view_matrix: glam::Mat4,
     proj_matrix: glam::Mat4,
     z_near: f32,
@@ -85,6 +150,11 @@ impl Default for PanOrbitCamera {
             mouse_speed: 0.005,
             zoom_speed: 0.5,
             pan_speed: 0.001,
+            pan_plane: None,
+            world_up: glam::Vec3::Z,
+            clamp_pitch: true,
+            auto_orbit: None,
+            animation: None,
             view_matrix: glam::Mat4::look_at_rh(position, target, glam::Vec3::Z),
             proj_matrix: glam::Mat4::perspective_rh(fovy, aspect, z_near, z_far),
         };
@@ -97,8 +167,7 @@ impl PanOrbitCamera {
     pub fn update(&mut self) {
         self.distance = f32::clamp(self.distance, self.distance_min, self.distance_max);
 
-        let offset = self.orientation * glam::vec3(0.0, -self.distance, 0.0);
-        let position = self.target + offset;
+        let position = self.eye();
 
         let up = self.orientation * glam::Vec3::Z;
 
@@ -111,39 +180,354 @@ impl PanOrbitCamera {
         } else {
             window_size.x as f32 / window_size.y as f32
         };
+        self.aspect = aspect;
         self.proj_matrix = glam::Mat4::perspective_rh(self.fovy, aspect, self.z_near, self.z_far);
     }
 
+    fn eye(&self) -> glam::Vec3 {
+        self.target + self.orientation * glam::vec3(0.0, -self.distance, 0.0)
+    }
+
+    fn orbit_rotation(&self, yaw: f32, mut pitch: f32) -> glam::Quat {
+        if self.clamp_pitch {
+            // Only limit movement towards the poles, so a view already past
+            // the limit can still come back
+            let forward = self.orientation * glam::Vec3::Y;
+            let elevation = forward.dot(self.world_up).clamp(-1.0, 1.0).asin();
+            pitch = pitch
+                .min((MAX_PITCH - elevation).max(0.0))
+                .max((-MAX_PITCH - elevation).min(0.0));
+        }
+
+        let yaw_q = glam::Quat::from_axis_angle(self.world_up, yaw);
+
+        let rt = self.orientation * glam::Vec3::X;
+        let pitch_q = glam::Quat::from_axis_angle(rt, pitch);
+
+        yaw_q * pitch_q
+    }
+
     pub fn orbit(&mut self, mouse_delta: glam::Vec2) {
         log::trace!("Mouse Delta: {mouse_delta}");
         let yaw = -mouse_delta.x * self.mouse_speed;
         let pitch = -mouse_delta.y * self.mouse_speed;
+        self.orbit_by(yaw, pitch);
+    }
 
-        let yaw_q = glam::Quat::from_axis_angle(glam::Vec3::Z, yaw);
+    /// Orbit the eye about `target` by `yaw` radians, counterclockwise about
+    /// `world_up`, and tilt the view direction up by `pitch` radians, which
+    /// moves the eye down
+    pub fn orbit_by(&mut self, yaw: f32, pitch: f32) {
+        self.animation = None;
+        self.orientation = (self.orbit_rotation(yaw, pitch) * self.orientation).normalize();
+        self.update();
+    }
 
-        let rt = self.orientation * glam::Vec3::X;
-        let pitch_q = glam::Quat::from_axis_angle(rt, pitch);
+    /// Orbit about `point` instead of `target`, e.g. a picked surface point.
+    /// The eye and target rotate rigidly about `point`, so the eye-to-point
+    /// distance is preserved and `target` stays the pivot for later orbits.
+    /// Call [`Self::set_pivot`] first to make `point` the pivot permanently.
+    pub fn orbit_around(&mut self, point: glam::Vec3, mouse_delta: glam::Vec2) {
+        log::trace!("Mouse Delta: {mouse_delta} around {point}");
+        self.animation = None;
+        let rotation = self.orbit_rotation(
+            -mouse_delta.x * self.mouse_speed,
+            -mouse_delta.y * self.mouse_speed,
+        );
+        self.target = point + rotation * (self.target - point);
+        self.orientation = (rotation * self.orientation).normalize();
+        self.update();
+    }
+
+    /// Make `point` the orbit target without moving the eye, turning the
+    /// camera to face it.
+    pub fn set_pivot(&mut self, point: glam::Vec3) {
+        let eye = self.eye();
+        let Some(forward) = (point - eye).try_normalize() else {
+            return;
+        };
+        let current = self.orientation * glam::Vec3::Y;
+        self.orientation =
+            (glam::Quat::from_rotation_arc(current, forward) * self.orientation).normalize();
+        self.target = point;
+        self.distance = eye.distance(point);
+        self.update();
+    }
+
+    /// Use `up` as the world's up axis, e.g. +Y for glTF scenes, turning the
+    /// camera with it so the view keeps the same relation to the new up.
+    /// The default camera switched to +Y up looks along -Z.
+    pub fn set_world_up(&mut self, up: glam::Vec3) {
+        let Some(up) = up.try_normalize() else {
+            return;
+        };
+        self.orientation =
+            (glam::Quat::from_rotation_arc(self.world_up, up) * self.orientation).normalize();
+        self.world_up = up;
+        self.update();
+    }
+
+    /// Roll the view by `radians` about the viewing direction, tilting the
+    /// horizon. The roll is part of `orientation`, so it is kept by later
+    /// orbits and pans until [`Self::reset_roll`].
+    pub fn roll(&mut self, radians: f32) {
+        let forward = self.orientation * glam::Vec3::Y;
+        self.orientation =
+            (glam::Quat::from_axis_angle(forward, radians) * self.orientation).normalize();
+        self.update();
+    }
 
-        self.orientation = ((yaw_q * pitch_q) * self.orientation).normalize();
+    /// Level the horizon, undoing any [`Self::roll`]. Does nothing while
+    /// looking straight up or down.
+    pub fn reset_roll(&mut self) {
+        let forward = self.orientation * glam::Vec3::Y;
+        let up = self.orientation * glam::Vec3::Z;
+        let Some(level_up) = project_onto_plane(self.world_up, forward).try_normalize() else {
+            return;
+        };
+        self.orientation =
+            (glam::Quat::from_rotation_arc(up, level_up) * self.orientation).normalize();
         self.update();
     }
 
     pub fn zoom(&mut self, mouse_scroll: f32) {
         log::trace!("Mouse Scroll: {mouse_scroll}");
-        if mouse_scroll == 0.0 {
+        self.zoom_by(mouse_scroll * self.zoom_speed);
+    }
+
+    /// Move the eye `amount` world units closer to `target`, or away when
+    /// negative, within `distance_min..distance_max`
+    pub fn zoom_by(&mut self, amount: f32) {
+        if amount == 0.0 {
             return;
         }
-        self.distance -= mouse_scroll * self.zoom_speed;
+        self.animation = None;
+        self.distance -= amount;
         self.update();
     }
 
     pub fn pan(&mut self, mouse_delta: glam::Vec2) {
         log::trace!("Mouse Delta: {mouse_delta}");
-
-        let rt = self.orientation * glam::Vec3::X;
-        let up = self.orientation * glam::Vec3::Z;
+        // Dragging moves the scene with the cursor, the camera the other way
         let pan_distance = self.distance * self.pan_speed;
-        self.target -= (rt * mouse_delta.x - up * mouse_delta.y) * pan_distance;
+        self.pan_by(-mouse_delta.x * pan_distance, mouse_delta.y * pan_distance);
+    }
+
+    /// Move the eye and `target` `dx` world units to the right of the view
+    /// and `dy` up it, or along `pan_plane` when set
+    pub fn pan_by(&mut self, dx: f32, dy: f32) {
+        self.animation = None;
+
+        let mut rt = self.orientation * glam::Vec3::X;
+        let mut up = self.orientation * glam::Vec3::Z;
+        if let Some(normal) = self.pan_plane {
+            // Slide along the plane: screen-up maps to the view direction
+            // flattened onto the plane, falling back to the view direction
+            // itself when looking along the plane.
+            let forward = self.orientation * glam::Vec3::Y;
+            rt = project_onto_plane(rt, normal).normalize_or_zero();
+            up = project_onto_plane(up, normal)
+                .try_normalize()
+                .unwrap_or_else(|| project_onto_plane(forward, normal).normalize_or_zero());
+        }
+        self.target += rt * dx + up * dy;
+        self.update();
+    }
+
+    /// Aim at the center of the box `min..max` and back off until its
+    /// bounding sphere fits the view with a small margin. The distance is
+    /// still clamped to `distance_min..distance_max`.
+    pub fn frame_bounds(&mut self, min: glam::Vec3, max: glam::Vec3) {
+        const MARGIN: f32 = 1.1;
+        let radius = 0.5 * min.distance(max);
+        let half_fovx = ((0.5 * self.fovy).tan() * self.aspect).atan();
+        let half_fov = half_fovx.min(0.5 * self.fovy);
+
+        self.target = 0.5 * (min + max);
+        self.distance = MARGIN * radius / half_fov.sin();
+        self.update();
+    }
+
+    /// Move smoothly to `target` and `distance` over `duration`, keeping the
+    /// current orientation, as [`Self::tick`] is called each frame. Orbiting,
+    /// panning or zooming cancels the transition.
+    pub fn animate_to(&mut self, target: glam::Vec3, distance: f32, duration: std::time::Duration) {
+        self.animate_to_view(target, distance, self.orientation, duration);
+    }
+
+    /// Like [`Self::animate_to`], also turning to `orientation`
+    pub fn animate_to_view(
+        &mut self,
+        target: glam::Vec3,
+        distance: f32,
+        orientation: glam::Quat,
+        duration: std::time::Duration,
+    ) {
+        self.animation = Some(CameraAnimation {
+            from: (self.target, self.distance, self.orientation),
+            to: (target, distance, orientation.normalize()),
+            elapsed: std::time::Duration::ZERO,
+            duration,
+        });
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.animation.is_some()
+    }
+
+    /// Advance the transition started by [`Self::animate_to`] by `dt`, with
+    /// ease-in-out timing, or else turn by `auto_orbit`. Pass
+    /// `Renderer::frame_delta` to follow the renderer's clock, including its
+    /// `fixed_timestep`.
+    pub fn tick(&mut self, dt: std::time::Duration) {
+        let Some(animation) = &mut self.animation else {
+            if let Some(speed) = self.auto_orbit {
+                self.orbit_by(speed * dt.as_secs_f32(), 0.0);
+            }
+            return;
+        };
+        animation.elapsed += dt;
+        let t = if animation.elapsed >= animation.duration {
+            1.0
+        } else {
+            animation.elapsed.as_secs_f32() / animation.duration.as_secs_f32()
+        };
+        let eased = if t < 0.5 {
+            4.0 * t * t * t
+        } else {
+            1.0 - (2.0 - 2.0 * t).powi(3) / 2.0
+        };
+
+        let (
+            (from_target, from_distance, from_orientation),
+            (to_target, to_distance, to_orientation),
+        ) = (animation.from, animation.to);
+        self.target = from_target.lerp(to_target, eased);
+        self.distance = from_distance + (to_distance - from_distance) * eased;
+        self.orientation = from_orientation.slerp(to_orientation, eased);
+        if t >= 1.0 {
+            self.animation = None;
+        }
+        self.update();
+    }
+
+    /// Constrain panning to the plane with the given normal, or pan freely in
+    /// the view plane when `None`.
+    pub fn set_pan_plane(&mut self, normal: Option<glam::Vec3>) {
+        self.pan_plane = normal.and_then(|n| n.try_normalize());
+    }
+}
+
+fn project_onto_plane(v: glam::Vec3, normal: glam::Vec3) -> glam::Vec3 {
+    let normal = normal.normalize_or_zero();
+    v - normal * v.dot(normal)
+}
+
+/// Pitch limit for [`FpsCamera`] and clamped [`PanOrbitCamera`] orbits,
+/// just short of straight up or down where the view would flip
+const MAX_PITCH: f32 = 89.0 * std::f32::consts::PI / 180.0;
+
+/// First-person fly camera for moving through a scene. Z is up; `yaw` is
+/// measured from +X towards +Y and `pitch` up from the horizon.
+#[derive(Debug)]
+pub struct FpsCamera {
+    pub position: glam::Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    view_matrix: glam::Mat4,
+    proj_matrix: glam::Mat4,
+    z_near: f32,
+    z_far: f32,
+    fovy: f32,
+}
+
+impl Camera for FpsCamera {
+    fn view_matrix(&self) -> &glam::Mat4 {
+        &self.view_matrix
+    }
+    fn view_matrix_mut(&mut self) -> &mut glam::Mat4 {
+        &mut self.view_matrix
+    }
+    fn proj_matrix(&self) -> &glam::Mat4 {
+        &self.proj_matrix
+    }
+    fn proj_matrix_mut(&mut self) -> &mut glam::Mat4 {
+        &mut self.proj_matrix
+    }
+}
+
+impl Default for FpsCamera {
+    fn default() -> Self {
+        let z_near = 0.1;
+        let z_far = 1000.0;
+        let fovy = 60_f32.to_radians();
+        // Same view as the default `PanOrbitCamera`, looking along +Y
+        let mut cam = Self {
+            position: glam::vec3(0.0, -10.0, 0.0),
+            yaw: std::f32::consts::FRAC_PI_2,
+            pitch: 0.0,
+            view_matrix: glam::Mat4::IDENTITY,
+            proj_matrix: glam::Mat4::perspective_rh(fovy, 16.0 / 9.0, z_near, z_far),
+            z_near,
+            z_far,
+            fovy,
+        };
+        cam.update();
+        cam
+    }
+}
+
+impl FpsCamera {
+    pub fn update(&mut self) {
+        self.pitch = self.pitch.clamp(-MAX_PITCH, MAX_PITCH);
+        self.view_matrix = glam::Mat4::look_to_rh(self.position, self.forward(), glam::Vec3::Z);
+    }
+
+    pub fn update_aspect(&mut self, window_size: glam::UVec2) {
+        let aspect = if window_size.x == 0 || window_size.y == 0 {
+            1.0
+        } else {
+            window_size.x as f32 / window_size.y as f32
+        };
+        self.proj_matrix = glam::Mat4::perspective_rh(self.fovy, aspect, self.z_near, self.z_far);
+    }
+
+    /// Unit view direction
+    pub fn forward(&self) -> glam::Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        glam::vec3(cos_pitch * cos_yaw, cos_pitch * sin_yaw, sin_pitch)
+    }
+
+    /// Unit horizontal direction to the right of the view
+    pub fn right(&self) -> glam::Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        glam::vec3(sin_yaw, -cos_yaw, 0.0)
+    }
+
+    /// Fly along the view direction, including up or down when pitched
+    pub fn move_forward(&mut self, amount: f32) {
+        self.position += self.forward() * amount;
+        self.update();
+    }
+
+    pub fn move_right(&mut self, amount: f32) {
+        self.position += self.right() * amount;
+        self.update();
+    }
+
+    /// Move along world up (+Z), regardless of where the camera looks
+    pub fn move_up(&mut self, amount: f32) {
+        self.position.z += amount;
+        self.update();
+    }
+
+    /// Turn by `yaw_delta` and `pitch_delta` radians. Positive yaw turns
+    /// left, positive pitch looks up; pitch stops short of straight up or
+    /// down.
+    pub fn look(&mut self, yaw_delta: f32, pitch_delta: f32) {
+        self.yaw = (self.yaw + yaw_delta).rem_euclid(std::f32::consts::TAU);
+        self.pitch += pitch_delta;
         self.update();
     }
 }
@@ -0,0 +1,89 @@
+/// Color ramps for mapping scalar values onto instance colors
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum Colormap {
+    /// Perceptually uniform dark purple to yellow
+    #[default]
+    Viridis,
+    /// High-contrast rainbow, dark blue through green to dark red
+    Turbo,
+    Grayscale,
+}
+
+pub const WHITE: glam::Vec4 = glam::Vec4::ONE;
+pub const BLACK: glam::Vec4 = glam::Vec4::W;
+pub const RED: glam::Vec4 = glam::vec4(1.0, 0.0, 0.0, 1.0);
+pub const GREEN: glam::Vec4 = glam::vec4(0.0, 1.0, 0.0, 1.0);
+pub const BLUE: glam::Vec4 = glam::vec4(0.0, 0.0, 1.0, 1.0);
+
+/// Linear RGBA from `0xRRGGBBAA`, with RGB in sRGB like CSS and image
+/// editor hex codes, and alpha as-is. `DrawCommandBuilder::with_color_srgb_u8`
+/// decodes bytes the same way; `with_color_u8` and float colors are taken
+/// as linear.
+pub fn from_hex(rgba: u32) -> glam::Vec4 {
+    let [r, g, b, a] = rgba.to_be_bytes().map(|c| f32::from(c) / 255.0);
+    srgb_to_linear(glam::vec3(r, g, b)).extend(a)
+}
+
+/// Viridis at ten evenly spaced points, sRGB
+const VIRIDIS: [[u8; 3]; 10] = [
+    [0x44, 0x01, 0x54],
+    [0x48, 0x28, 0x78],
+    [0x3e, 0x49, 0x89],
+    [0x31, 0x68, 0x8e],
+    [0x26, 0x82, 0x8e],
+    [0x1f, 0x9e, 0x89],
+    [0x35, 0xb7, 0x79],
+    [0x6e, 0xce, 0x58],
+    [0xb5, 0xde, 0x2b],
+    [0xfd, 0xe7, 0x25],
+];
+
+/// Linear RGBA for `t` in [0, 1] along `map`. `t` is clamped.
+pub fn sample_colormap(map: Colormap, t: f32) -> glam::Vec4 {
+    let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+    let srgb = match map {
+        Colormap::Viridis => sample_lut(&VIRIDIS, t),
+        Colormap::Turbo => turbo(t),
+        Colormap::Grayscale => glam::Vec3::splat(t),
+    };
+    srgb_to_linear(srgb).extend(1.0)
+}
+
+fn sample_lut(lut: &[[u8; 3]], t: f32) -> glam::Vec3 {
+    let position = t * (lut.len() - 1) as f32;
+    let i = (position as usize).min(lut.len() - 2);
+    let [a, b] = [lut[i], lut[i + 1]].map(|c| glam::Vec3::from(c.map(f32::from)) / 255.0);
+    a.lerp(b, position - i as f32)
+}
+
+/// Polynomial fit of Turbo (Mikhailov, 2019), sRGB. Coefficients of
+/// 1, t, t^2, t^3 and t^4, t^5 per channel.
+const TURBO_RGB4: [[f32; 4]; 3] = [
+    [0.135_721_38, 4.615_392_6, -42.660_32, 132.131_08],
+    [0.091_402_61, 2.194_188_4, 4.842_966_6, -14.185_033],
+    [0.106_673_3, 12.641_946, -60.582_05, 110.362_77],
+];
+const TURBO_RGB2: [[f32; 2]; 3] = [
+    [-152.942_4, 59.286_38],
+    [4.277_298_6, 2.829_566],
+    [-89.903_11, 27.348_25],
+];
+
+fn turbo(t: f32) -> glam::Vec3 {
+    let v4 = glam::vec4(1.0, t, t * t, t * t * t);
+    let v2 = glam::vec2(v4.z * v4.z, v4.w * v4.z);
+    let channel = |i: usize| {
+        v4.dot(glam::Vec4::from(TURBO_RGB4[i])) + v2.dot(glam::Vec2::from(TURBO_RGB2[i]))
+    };
+    glam::vec3(channel(0), channel(1), channel(2)).clamp(glam::Vec3::ZERO, glam::Vec3::ONE)
+}
+
+fn srgb_to_linear(srgb: glam::Vec3) -> glam::Vec3 {
+    srgb.map(|c| {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    })
+}
@@ -1,10 +1,244 @@
-use super::mesh::MeshType;
+use super::color::{self, Colormap};
+use super::mesh::{self, MeshType};
 use super::renderer::Instance;
 
+/// Index of a command in `Renderer::commands`. Ids are only valid until the
+/// command list is next cleared.
+pub type CommandId = usize;
+
+/// Stable reference to a command added with `Renderer::add_command`. Other
+/// commands coming and going don't affect it, and a removed command's
+/// handle is never valid again.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct CommandHandle {
+    index: u32,
+    generation: u32,
+}
+
+/// Reference to a batch added with `Renderer::add_instanced`. Distinct from
+/// [`CommandHandle`] so one can't be passed where the other is expected.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct BatchHandle(pub(crate) CommandHandle);
+
+/// Commands kept across frames, addressed by [`CommandHandle`]. Slots of
+/// removed commands are reused under a new generation. Also holds
+/// [`InstanceBatch`]es.
+#[derive(Debug)]
+pub struct RetainedCommands<T = DrawCommand> {
+    slots: Vec<(u32, Option<T>)>,
+    free: Vec<u32>,
+    len: usize,
+    revision: u64,
+}
+
+impl<T> Default for RetainedCommands<T> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+            revision: 0,
+        }
+    }
+}
+
+impl<T> RetainedCommands<T> {
+    /// Bumped by every insert, removal, `clear` and `get_mut`, so callers
+    /// can skip work derived from the commands while it is unchanged
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn insert(&mut self, command: T) -> CommandHandle {
+        self.revision += 1;
+        self.len += 1;
+        if let Some(index) = self.free.pop() {
+            let slot = &mut self.slots[index as usize];
+            slot.1 = Some(command);
+            return CommandHandle {
+                index,
+                generation: slot.0,
+            };
+        }
+        self.slots.push((0, Some(command)));
+        CommandHandle {
+            index: (self.slots.len() - 1) as u32,
+            generation: 0,
+        }
+    }
+
+    pub fn remove(&mut self, handle: CommandHandle) -> Option<T> {
+        let slot = self.slots.get_mut(handle.index as usize)?;
+        if slot.0 != handle.generation {
+            return None;
+        }
+        let command = slot.1.take()?;
+        slot.0 = slot.0.wrapping_add(1);
+        self.free.push(handle.index);
+        self.len -= 1;
+        self.revision += 1;
+        Some(command)
+    }
+
+    /// Remove everything. Existing handles are never valid again.
+    pub fn clear(&mut self) {
+        self.revision += 1;
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if slot.1.take().is_some() {
+                slot.0 = slot.0.wrapping_add(1);
+                self.free.push(index as u32);
+            }
+        }
+        self.len = 0;
+    }
+
+    pub fn get(&self, handle: CommandHandle) -> Option<&T> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|slot| slot.0 == handle.generation)
+            .and_then(|slot| slot.1.as_ref())
+    }
+
+    pub fn get_mut(&mut self, handle: CommandHandle) -> Option<&mut T> {
+        let command = self
+            .slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.0 == handle.generation)
+            .and_then(|slot| slot.1.as_mut())?;
+        self.revision += 1;
+        Some(command)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (CommandHandle, &T)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (generation, command))| {
+                let handle = CommandHandle {
+                    index: index as u32,
+                    generation: *generation,
+                };
+                command.as_ref().map(|command| (handle, command))
+            })
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Many instances of one mesh drawn without per-command work, e.g. a point
+/// cloud or particles, see `Renderer::add_instanced`
+#[derive(Debug, Clone)]
+pub struct InstanceBatch {
+    pub mesh_type: MeshType,
+    pub instances: Vec<Instance>,
+    /// GPU copy of `instances`, written only when they are set
+    pub buffer: wgpu::Buffer,
+}
+
+impl InstanceBatch {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mesh_type: MeshType,
+        instances: Vec<Instance>,
+    ) -> Self {
+        let buffer = create_batch_buffer(device, mesh_type, instances.len());
+        let mut batch = Self {
+            mesh_type,
+            instances: Vec::new(),
+            buffer,
+        };
+        batch.set_instances(device, queue, instances);
+        batch
+    }
+
+    /// Replace the instances and upload them, growing the buffer if needed
+    pub(crate) fn set_instances(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        instances: Vec<Instance>,
+    ) {
+        let max = mesh::max_instances_per_buffer(device.limits().max_buffer_size);
+        if instances.len() > max {
+            log::warn!(
+                "Instance batch of {} {:?} instances exceeds the buffer limit, drawing {max}",
+                instances.len(),
+                self.mesh_type
+            );
+        }
+        let size = (instances.len().min(max) * std::mem::size_of::<Instance>()) as u64;
+        if size > self.buffer.size() {
+            self.buffer = create_batch_buffer(device, self.mesh_type, instances.len().min(max));
+        }
+        if size > 0 {
+            queue.write_buffer(
+                &self.buffer,
+                0,
+                bytemuck::cast_slice(&instances[..instances.len().min(max)]),
+            );
+        }
+        self.instances = instances;
+    }
+
+    /// Number of instances in `buffer`
+    pub fn uploaded_count(&self) -> u32 {
+        (self.buffer.size() / std::mem::size_of::<Instance>() as u64)
+            .min(self.instances.len() as u64) as u32
+    }
+}
+
+/// Instance buffer holding `capacity` instances, at least one so it can
+/// always be bound
+fn create_batch_buffer(
+    device: &wgpu::Device,
+    mesh_type: MeshType,
+    capacity: usize,
+) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(&mesh::label("Batch Instance Buffer", mesh_type)),
+        size: (capacity.max(1) * std::mem::size_of::<Instance>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// How an outlined command's hull is drawn
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OutlineStyle {
+    /// Scale of the edge hull about the instance origin, slightly above 1 so
+    /// edges win the depth test against their own faces
+    pub scale: f32,
+    pub color: glam::Vec4,
+}
+
+impl Default for OutlineStyle {
+    fn default() -> Self {
+        Self {
+            scale: 1.005,
+            color: glam::Vec4::ONE,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DrawCommand {
     pub mesh_type: MeshType,
     pub instance: Instance,
+    /// Outline this command even while the renderer's outlines are off
+    pub outline: bool,
+    /// Outline look for this command alone, instead of the renderer's.
+    /// `OutlineMode::EdgeDetect` and outline MSAA draw every outline in the
+    /// renderer's style and ignore it.
+    pub outline_style: Option<OutlineStyle>,
 }
 
 impl DrawCommand {
@@ -12,16 +246,34 @@ impl DrawCommand {
         Self {
             mesh_type,
             instance,
+            outline: false,
+            outline_style: None,
         }
     }
+
+    pub fn set_model_matrix(&mut self, model_matrix: glam::Mat4) {
+        self.instance.model_matrix = model_matrix;
+    }
 }
 
 pub struct DrawCommandBuilder {
     pub mesh_type: MeshType,
     pub position: glam::Vec3,
-    pub rotation: glam::Mat3,
+    pub rotation: glam::Quat,
     pub scale: f32,
     pub color: glam::Vec4,
+    /// Used as-is by `build` when set, ignoring position, rotation and scale
+    pub model_matrix: Option<glam::Mat4>,
+    pub uv_region: glam::Vec4,
+    pub outline: bool,
+    pub outline_style: Option<OutlineStyle>,
+}
+
+/// A white unit cube at the origin
+impl Default for DrawCommandBuilder {
+    fn default() -> Self {
+        Self::new(MeshType::Cube)
+    }
 }
 
 impl DrawCommandBuilder {
@@ -29,24 +281,81 @@ impl DrawCommandBuilder {
         Self {
             mesh_type,
             position: glam::Vec3::default(),
-            rotation: glam::Mat3::default(),
+            rotation: glam::Quat::IDENTITY,
             scale: 1.0,
             color: [1.0, 1.0, 1.0, 1.0].into(),
+            model_matrix: None,
+            uv_region: Instance::FULL_UV_REGION,
+            outline: false,
+            outline_style: None,
         }
     }
 
+    /// `MeshType::Arrow` stretched from `start` to `end`, `thickness` across
+    /// the shaft, e.g. for plotting a force or velocity
+    pub fn arrow_between(start: glam::Vec3, end: glam::Vec3, thickness: f32) -> Self {
+        let direction = end - start;
+        let rotation =
+            glam::Quat::from_rotation_arc(glam::Vec3::Y, direction.normalize_or(glam::Vec3::Y));
+        Self::new(MeshType::Arrow).with_model_matrix(glam::Mat4::from_scale_rotation_translation(
+            glam::vec3(thickness, direction.length(), thickness),
+            rotation,
+            start,
+        ))
+    }
+
     pub fn with_position(self, position: glam::Vec3) -> Self {
         Self { position, ..self }
     }
 
     pub fn with_rotation(self, rotation: glam::Mat3) -> Self {
+        Self {
+            rotation: glam::Quat::from_mat3(&rotation),
+            ..self
+        }
+    }
+
+    /// Orientation as a quaternion, e.g. from a physics engine
+    pub fn with_rotation_quat(self, rotation: glam::Quat) -> Self {
         Self { rotation, ..self }
     }
 
+    /// Orientation from XYZ Euler angles in radians
+    pub fn with_rotation_euler(self, angles: glam::Vec3) -> Self {
+        Self {
+            rotation: glam::Quat::from_euler(glam::EulerRot::XYZ, angles.x, angles.y, angles.z),
+            ..self
+        }
+    }
+
     pub fn with_scale(self, scale: f32) -> Self {
         Self { scale, ..self }
     }
 
+    /// Use a full transform, e.g. from a physics or ECS system, instead of
+    /// composing one from position, rotation and scale
+    pub fn with_model_matrix(self, model_matrix: glam::Mat4) -> Self {
+        Self {
+            model_matrix: Some(model_matrix),
+            ..self
+        }
+    }
+
+    /// Alias of [`Self::with_model_matrix`], keeping non-uniform scale and
+    /// shear as given
+    pub fn with_transform(self, transform: glam::Mat4) -> Self {
+        self.with_model_matrix(transform)
+    }
+
+    /// Sample only the atlas region at `offset` of size `scale`, both in UV
+    /// units, so many instances can show different sprites in one draw
+    pub fn with_uv_region(self, offset: glam::Vec2, scale: glam::Vec2) -> Self {
+        Self {
+            uv_region: glam::Vec4::new(offset.x, offset.y, scale.x, scale.y),
+            ..self
+        }
+    }
+
     pub fn with_color(self, r: f32, g: f32, b: f32, a: f32) -> Self {
         Self {
             color: glam::Vec4::new(r, g, b, a),
@@ -54,6 +363,55 @@ impl DrawCommandBuilder {
         }
     }
 
+    pub fn with_color_rgb(self, r: f32, g: f32, b: f32) -> Self {
+        self.with_color(r, g, b, 1.0)
+    }
+
+    /// Color from an sRGB `0xRRGGBBAA` hex code, see [`color::from_hex`]
+    pub fn with_color_hex(self, rgba: u32) -> Self {
+        Self {
+            color: color::from_hex(rgba),
+            ..self
+        }
+    }
+
+    pub fn red(self) -> Self {
+        Self {
+            color: color::RED,
+            ..self
+        }
+    }
+
+    pub fn green(self) -> Self {
+        Self {
+            color: color::GREEN,
+            ..self
+        }
+    }
+
+    pub fn blue(self) -> Self {
+        Self {
+            color: color::BLUE,
+            ..self
+        }
+    }
+
+    pub fn white(self) -> Self {
+        Self {
+            color: color::WHITE,
+            ..self
+        }
+    }
+
+    pub fn black(self) -> Self {
+        Self {
+            color: color::BLACK,
+            ..self
+        }
+    }
+
+    /// Linear color from bytes, each divided by 255 with no sRGB decoding.
+    /// See [`Self::with_color_srgb_u8`] for bytes from a color picker.
     pub fn with_color_u8(self, r: u8, g: u8, b: u8, a: u8) -> Self {
         Self {
             color: glam::Vec4::new(r as f32, g as f32, b as f32, a as f32) / 255.0,
@@ -61,6 +419,35 @@ impl DrawCommandBuilder {
         }
     }
 
+    /// Color from sRGB bytes like CSS `rgba()`, with alpha as-is, decoded
+    /// to linear like [`Self::with_color_hex`]
+    pub fn with_color_srgb_u8(self, r: u8, g: u8, b: u8, a: u8) -> Self {
+        self.with_color_hex(u32::from_be_bytes([r, g, b, a]))
+    }
+
+    /// Color from `map` at `t` in [0, 1], e.g. a normalized scalar value
+    pub fn with_colormap(self, map: Colormap, t: f32) -> Self {
+        Self {
+            color: color::sample_colormap(map, t),
+            ..self
+        }
+    }
+
+    /// Outline this command on its own, e.g. a selected object, while the
+    /// rest of the scene is drawn without outlines
+    pub fn with_outline(self, outline: bool) -> Self {
+        Self { outline, ..self }
+    }
+
+    /// Outline this command at `scale` in `color` rather than the
+    /// renderer's outline style, e.g. to make a selection stand out
+    pub fn with_outline_style(self, scale: f32, color: glam::Vec4) -> Self {
+        Self {
+            outline_style: Some(OutlineStyle { scale, color }),
+            ..self
+        }
+    }
+
     pub fn build(self) -> DrawCommand {
         let DrawCommandBuilder {
             mesh_type,
@@ -68,21 +455,53 @@ impl DrawCommandBuilder {
             rotation,
             scale,
             color,
+            model_matrix,
+            uv_region,
+            outline,
+            outline_style,
         } = self;
 
-        let rotation = glam::Quat::from_mat3(&rotation);
-        let model_matrix = glam::Mat4::from_scale_rotation_translation(
-            glam::Vec3::splat(scale),
-            rotation,
-            position,
-        );
+        let model_matrix = model_matrix.unwrap_or_else(|| {
+            glam::Mat4::from_scale_rotation_translation(
+                glam::Vec3::splat(scale),
+                rotation,
+                position,
+            )
+        });
 
         DrawCommand {
             mesh_type,
             instance: Instance {
                 model_matrix,
                 color,
+                uv_region,
             },
+            outline,
+            outline_style,
         }
     }
 }
+
+/// One command per point of a `counts.x * counts.y * counts.z` lattice starting
+/// at `origin`, ordered x-fastest.
+pub fn mesh_grid(
+    mesh_type: MeshType,
+    counts: glam::UVec3,
+    spacing: glam::Vec3,
+    origin: glam::Vec3,
+    scale: f32,
+    color: glam::Vec4,
+) -> impl Iterator<Item = DrawCommand> {
+    (0..counts.z).flat_map(move |k| {
+        (0..counts.y).flat_map(move |j| {
+            (0..counts.x).map(move |i| {
+                let offset = glam::uvec3(i, j, k).as_vec3() * spacing;
+                DrawCommandBuilder::new(mesh_type)
+                    .with_position(origin + offset)
+                    .with_scale(scale)
+                    .with_color(color.x, color.y, color.z, color.w)
+                    .build()
+            })
+        })
+    })
+}
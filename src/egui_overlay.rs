@@ -0,0 +1,100 @@
+use super::renderer::Renderer;
+
+/// egui UI drawn over the scene into the surface texture. Call `run` with
+/// each frame's input to lay out the UI, then `paint` from
+/// `Renderer::render_with_overlay`.
+pub struct EguiOverlay {
+    pub context: egui::Context,
+    pub renderer: egui_wgpu::Renderer,
+    /// Output of the last `run`, waiting to be painted
+    paint_jobs: Vec<egui::ClippedPrimitive>,
+    textures_delta: egui::TexturesDelta,
+    pixels_per_point: f32,
+}
+
+impl EguiOverlay {
+    pub fn new(device: &wgpu::Device, surface_format: wgpu::TextureFormat) -> Self {
+        Self {
+            context: egui::Context::default(),
+            renderer: egui_wgpu::Renderer::new(device, surface_format, None, 1, false),
+            paint_jobs: Vec::new(),
+            textures_delta: egui::TexturesDelta::default(),
+            pixels_per_point: 1.0,
+        }
+    }
+
+    /// Overlay for `renderer`'s device and surface format
+    pub fn for_renderer(renderer: &Renderer) -> Self {
+        Self::new(&renderer.device, renderer.surface_config.format)
+    }
+
+    /// Lay out one frame of UI from `input`, e.g. collected by `egui-winit`.
+    /// The returned output carries cursor and clipboard requests back to the
+    /// windowing layer.
+    pub fn run(
+        &mut self,
+        input: egui::RawInput,
+        run_ui: impl FnMut(&egui::Context),
+    ) -> egui::PlatformOutput {
+        let output = self.context.run(input, run_ui);
+        self.paint_jobs = self
+            .context
+            .tessellate(output.shapes, output.pixels_per_point);
+        self.textures_delta.append(output.textures_delta);
+        self.pixels_per_point = output.pixels_per_point;
+        output.platform_output
+    }
+
+    /// Draw the UI from the last `run` over `view`, which is `size_in_pixels`
+    /// large. Matches the `Renderer::render_with_overlay` callback.
+    pub fn paint(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        size_in_pixels: [u32; 2],
+    ) {
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels,
+            pixels_per_point: self.pixels_per_point,
+        };
+        for (id, image_delta) in &self.textures_delta.set {
+            self.renderer
+                .update_texture(device, queue, *id, image_delta);
+        }
+        // Only non-empty with custom paint callbacks, which must run first
+        let callback_buffers = self.renderer.update_buffers(
+            device,
+            queue,
+            encoder,
+            &self.paint_jobs,
+            &screen_descriptor,
+        );
+        queue.submit(callback_buffers);
+
+        let mut render_pass = encoder
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Egui Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            })
+            .forget_lifetime();
+        self.renderer
+            .render(&mut render_pass, &self.paint_jobs, &screen_descriptor);
+        drop(render_pass);
+
+        for id in std::mem::take(&mut self.textures_delta).free {
+            self.renderer.free_texture(&id);
+        }
+    }
+}
@@ -0,0 +1,20 @@
+/// Errors returned by the public API
+#[derive(Debug, thiserror::Error)]
+pub enum RaidenError {
+    #[error("No suitable GPU adapter: {0}")]
+    AdapterUnavailable(#[from] wgpu::RequestAdapterError),
+    #[error("Failed to create surface: {0}")]
+    SurfaceCreation(#[from] wgpu::CreateSurfaceError),
+    #[error("Failed to request device: {0}")]
+    DeviceRequest(#[from] wgpu::RequestDeviceError),
+    #[error("Failed to compile shader {name}: {message}")]
+    ShaderCompilation { name: String, message: String },
+    #[error("Failed to import mesh: {0}")]
+    MeshImport(String),
+    #[error("Failed to capture frame: {0}")]
+    FrameCapture(String),
+    #[error("Failed to save image: {0}")]
+    ImageSave(#[from] image::ImageError),
+    #[error("Failed to save or load scene: {0}")]
+    Scene(String),
+}
@@ -0,0 +1,294 @@
+#[cfg(any(feature = "obj", feature = "gltf"))]
+use super::error::RaidenError;
+use super::mesh::Vertex;
+#[cfg(feature = "gltf")]
+use super::renderer::Instance;
+
+/// Up axis of the source asset's coordinate system. The crate's primitives
+/// and camera are right-handed Z-up.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum UpAxis {
+    X,
+    /// glTF, and most OBJ exports
+    Y,
+    #[default]
+    Z,
+}
+
+/// Coordinate fix-up applied to imported geometry as the mesh is built
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ImportTransform {
+    pub up_axis: UpAxis,
+    pub scale: f32,
+    /// Reverse triangle winding, for assets authored clockwise
+    pub flip_winding: bool,
+}
+
+impl Default for ImportTransform {
+    fn default() -> Self {
+        Self {
+            up_axis: UpAxis::Z,
+            scale: 1.0,
+            flip_winding: false,
+        }
+    }
+}
+
+impl ImportTransform {
+    /// Rotation taking the source up axis onto +Z, keeping handedness
+    pub fn rotation(&self) -> glam::Mat3 {
+        match self.up_axis {
+            UpAxis::X => glam::Mat3::from_rotation_y(-std::f32::consts::FRAC_PI_2),
+            UpAxis::Y => glam::Mat3::from_rotation_x(std::f32::consts::FRAC_PI_2),
+            UpAxis::Z => glam::Mat3::IDENTITY,
+        }
+    }
+
+    /// Rotation and scale as one matrix
+    pub fn matrix(&self) -> glam::Mat4 {
+        glam::Mat4::from_mat3(self.rotation() * self.scale)
+    }
+
+    /// Move a model matrix authored in source coordinates into the imported
+    /// space, `T · model · T⁻¹`, so it places geometry run through
+    /// [`Self::apply`] where the source placed the original
+    pub fn apply_to_model(&self, model: glam::Mat4) -> glam::Mat4 {
+        let matrix = self.matrix();
+        matrix * model * matrix.inverse()
+    }
+
+    pub fn apply(&self, vertices: &mut [Vertex], indices: &mut [u32]) {
+        let rotation = self.rotation();
+        for vertex in vertices.iter_mut() {
+            vertex.position = rotation * vertex.position * self.scale;
+            vertex.normal = (rotation * vertex.normal).normalize_or_zero();
+        }
+        if self.flip_winding {
+            for triangle in indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+    }
+}
+
+/// Triangle list geometry read from a model file, ready for
+/// `Mesh::from_data` or `Renderer::register_mesh`
+#[derive(Debug, Default, Clone)]
+pub struct MeshData {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    /// Every triangle edge once, as a line list
+    pub edge_indices: Vec<u32>,
+}
+
+/// Read every group of an OBJ file into one mesh, triangulating polygons
+/// and computing smooth normals for groups without them. Coordinates are
+/// kept as-is; most OBJ exports are Y-up, so apply an [`ImportTransform`]
+/// or use `Mesh::from_obj_with`.
+#[cfg(feature = "obj")]
+pub fn load_obj(path: impl AsRef<std::path::Path>) -> Result<MeshData, RaidenError> {
+    let path = path.as_ref();
+    let options = tobj::LoadOptions {
+        triangulate: true,
+        single_index: true,
+        ..Default::default()
+    };
+    let (models, _materials) = tobj::load_obj(path, &options)
+        .map_err(|err| RaidenError::MeshImport(format!("{}: {err}", path.display())))?;
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for model in &models {
+        let mesh = &model.mesh;
+        let offset = vertices.len();
+        let vertex_count = mesh.positions.len() / 3;
+        let has_normals = mesh.normals.len() == mesh.positions.len();
+        let has_uvs = mesh.texcoords.len() / 2 == vertex_count;
+        vertices.extend((0..vertex_count).map(|i| Vertex {
+            position: glam::Vec3::from_slice(&mesh.positions[3 * i..]),
+            normal: if has_normals {
+                glam::Vec3::from_slice(&mesh.normals[3 * i..])
+            } else {
+                glam::Vec3::ZERO
+            },
+            uv: if has_uvs {
+                glam::Vec2::from_slice(&mesh.texcoords[2 * i..])
+            } else {
+                glam::Vec2::ZERO
+            },
+            ..Default::default()
+        }));
+        let group_indices: Vec<u32> = mesh
+            .indices
+            .iter()
+            .map(|&index| (offset + index as usize) as u32)
+            .collect();
+        if !has_normals {
+            smooth_normals(&mut vertices, &group_indices);
+        }
+        indices.extend(group_indices);
+    }
+
+    let edge_indices = triangle_edges(&indices);
+    Ok(MeshData {
+        vertices,
+        indices,
+        edge_indices,
+    })
+}
+
+/// Area-weighted average of the face normals around each vertex
+#[cfg(any(feature = "obj", feature = "gltf"))]
+fn smooth_normals(vertices: &mut [Vertex], indices: &[u32]) {
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| vertices[triangle[i] as usize].position);
+        let face_normal = (b - a).cross(c - a);
+        for &index in triangle {
+            vertices[index as usize].normal += face_normal;
+        }
+    }
+    for &index in indices {
+        let vertex = &mut vertices[index as usize];
+        vertex.normal = vertex.normal.normalize_or_zero();
+    }
+}
+
+/// Line list with each edge shared by triangles in `indices` once
+#[cfg(any(feature = "obj", feature = "gltf"))]
+fn triangle_edges(indices: &[u32]) -> Vec<u32> {
+    let mut seen = std::collections::HashSet::new();
+    let mut edges = Vec::new();
+    for triangle in indices.chunks_exact(3) {
+        for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+            let edge = (triangle[a].min(triangle[b]), triangle[a].max(triangle[b]));
+            if seen.insert(edge) {
+                edges.extend([edge.0, edge.1]);
+            }
+        }
+    }
+    edges
+}
+
+/// Read the first primitive of the first mesh in a glTF 2.0 file, plus one
+/// instance per node of the default scene that uses that mesh, placed by the
+/// node's world transform and colored by the material's base color factor.
+///
+/// Animations, skins, morph targets, cameras, lights and textures are
+/// ignored, as are other meshes and primitives. Only triangle lists are
+/// supported. Coordinates are kept as-is, and glTF is Y-up; see
+/// `Mesh::from_gltf_with` to import them Z-up.
+#[cfg(feature = "gltf")]
+pub fn load_gltf(
+    path: impl AsRef<std::path::Path>,
+) -> Result<(MeshData, Vec<Instance>), RaidenError> {
+    let path = path.as_ref();
+    let error = |message: String| RaidenError::MeshImport(format!("{}: {message}", path.display()));
+    let gltf = gltf::Gltf::open(path).map_err(|err| error(err.to_string()))?;
+    let buffers = gltf::import_buffers(&gltf.document, path.parent(), gltf.blob.clone())
+        .map_err(|err| error(err.to_string()))?;
+
+    let mesh = gltf
+        .document
+        .meshes()
+        .next()
+        .ok_or_else(|| error("no meshes".to_string()))?;
+    let primitive = mesh
+        .primitives()
+        .next()
+        .ok_or_else(|| error("mesh has no primitives".to_string()))?;
+    if primitive.mode() != gltf::mesh::Mode::Triangles {
+        return Err(error(format!(
+            "unsupported primitive mode {:?}",
+            primitive.mode()
+        )));
+    }
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+    let mut vertices: Vec<Vertex> = reader
+        .read_positions()
+        .ok_or_else(|| error("primitive has no positions".to_string()))?
+        .map(|position| Vertex {
+            position: position.into(),
+            ..Default::default()
+        })
+        .collect();
+    if let Some(colors) = reader.read_colors(0) {
+        for (vertex, color) in vertices.iter_mut().zip(colors.into_rgb_f32()) {
+            vertex.color = color.into();
+        }
+    }
+    if let Some(uvs) = reader.read_tex_coords(0) {
+        for (vertex, uv) in vertices.iter_mut().zip(uvs.into_f32()) {
+            vertex.uv = uv.into();
+        }
+    }
+
+    let indices: Vec<u32> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..vertices.len() as u32).collect(),
+    };
+
+    match reader.read_normals() {
+        Some(normals) => {
+            for (vertex, normal) in vertices.iter_mut().zip(normals) {
+                vertex.normal = normal.into();
+            }
+        }
+        None => smooth_normals(&mut vertices, &indices),
+    }
+
+    let color = glam::Vec4::from(
+        primitive
+            .material()
+            .pbr_metallic_roughness()
+            .base_color_factor(),
+    );
+    let mut instances = Vec::new();
+    if let Some(scene) = gltf
+        .document
+        .default_scene()
+        .or_else(|| gltf.document.scenes().next())
+    {
+        for node in scene.nodes() {
+            collect_gltf_instances(
+                &node,
+                glam::Mat4::IDENTITY,
+                mesh.index(),
+                color,
+                &mut instances,
+            );
+        }
+    }
+
+    let edge_indices = triangle_edges(&indices);
+    Ok((
+        MeshData {
+            vertices,
+            indices,
+            edge_indices,
+        },
+        instances,
+    ))
+}
+
+/// Instances of mesh `mesh_index` under `node`, depth first
+#[cfg(feature = "gltf")]
+fn collect_gltf_instances(
+    node: &gltf::Node,
+    parent: glam::Mat4,
+    mesh_index: usize,
+    color: glam::Vec4,
+    instances: &mut Vec<Instance>,
+) {
+    let model_matrix = parent * glam::Mat4::from_cols_array_2d(&node.transform().matrix());
+    if node.mesh().is_some_and(|mesh| mesh.index() == mesh_index) {
+        instances.push(Instance {
+            model_matrix,
+            color,
+            uv_region: Instance::FULL_UV_REGION,
+        });
+    }
+    for child in node.children() {
+        collect_gltf_instances(&child, model_matrix, mesh_index, color, instances);
+    }
+}
@@ -4,7 +4,865 @@ pub mod commands;
 pub mod primitives;
 pub mod camera;
 pub mod shaders;
+pub mod import;
+pub mod error;
+pub mod color;
+pub mod timing;
+#[cfg(feature = "egui")]
+pub mod egui_overlay;
 
 #[cfg(test)]
 mod tests {
+    use super::camera::{Camera, PanOrbitCamera};
+    use super::color::{self, Colormap};
+    use super::commands;
+    use super::import::{ImportTransform, UpAxis};
+    use super::mesh::{self, MeshType, Vertex};
+    use super::renderer::{self, BlendMode, Instance, OffscreenRenderer};
+    use std::collections::HashMap;
+
+    /// Any adapter, including software ones, so GPU tests can run in CI.
+    /// `None` skips the test when no adapter is available at all.
+    fn test_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+        let adapter =
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+                .ok()?;
+        pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            required_limits:
+                wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits()),
+            ..Default::default()
+        }))
+        .ok()
+    }
+
+    #[test]
+    fn mesh_grid_places_one_command_per_lattice_point() {
+        let origin = glam::vec3(1.0, 2.0, 3.0);
+        let spacing = glam::vec3(0.5, 1.0, 2.0);
+        let grid: Vec<_> = commands::mesh_grid(
+            MeshType::Cube,
+            glam::UVec3::splat(3),
+            spacing,
+            origin,
+            0.1,
+            glam::Vec4::ONE,
+        )
+        .collect();
+
+        assert_eq!(grid.len(), 27);
+        let mut idx = 0;
+        for k in 0..3 {
+            for j in 0..3 {
+                for i in 0..3 {
+                    let expected = origin + glam::vec3(i as f32, j as f32, k as f32) * spacing;
+                    let position = grid[idx].instance.model_matrix.w_axis.truncate();
+                    assert!(
+                        position.abs_diff_eq(expected, 1e-6),
+                        "{position} != {expected}"
+                    );
+                    assert_eq!(grid[idx].mesh_type, MeshType::Cube);
+                    idx += 1;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn import_transform_converts_y_up_to_z_up() {
+        let normal = glam::Vec3::Z;
+        let mut vertices = [
+            Vertex::new(glam::vec3(0.0, 0.0, 0.0), glam::Vec3::ONE, normal),
+            Vertex::new(glam::vec3(1.0, 0.0, 0.0), glam::Vec3::ONE, normal),
+            Vertex::new(glam::vec3(0.0, 1.0, 0.0), glam::Vec3::ONE, normal),
+        ];
+        let mut indices = [0, 1, 2];
+        let transform = ImportTransform {
+            up_axis: UpAxis::Y,
+            scale: 2.0,
+            flip_winding: false,
+        };
+        transform.apply(&mut vertices, &mut indices);
+
+        // The Y-up apex now points up +Z, the Y-up front (+Z) faces -Y
+        let expected = [
+            glam::vec3(0.0, 0.0, 0.0),
+            glam::vec3(2.0, 0.0, 0.0),
+            glam::vec3(0.0, 0.0, 2.0),
+        ];
+        for (vertex, expected) in vertices.iter().zip(expected) {
+            assert!(vertex.position.abs_diff_eq(expected, 1e-6));
+            assert!(vertex.normal.abs_diff_eq(glam::Vec3::NEG_Y, 1e-6));
+        }
+        assert_eq!(indices, [0, 1, 2]);
+
+        let flip = ImportTransform {
+            flip_winding: true,
+            ..Default::default()
+        };
+        flip.apply(&mut vertices, &mut indices);
+        assert_eq!(indices, [0, 2, 1]);
+    }
+
+    #[test]
+    fn import_transform_moves_node_placement_with_the_geometry() {
+        let transform = ImportTransform {
+            up_axis: UpAxis::Y,
+            scale: 2.0,
+            flip_winding: false,
+        };
+        // A Y-up node lifted 3 up and turned about its up axis
+        let model = glam::Mat4::from_rotation_translation(
+            glam::Quat::from_rotation_y(0.7),
+            glam::vec3(1.0, 3.0, 0.0),
+        );
+        let point = glam::vec3(0.5, 1.0, -0.25);
+
+        let placed = transform.apply_to_model(model);
+        let expected = transform
+            .matrix()
+            .transform_point3(model.transform_point3(point));
+        let imported = transform.matrix().transform_point3(point);
+        assert!(
+            placed
+                .transform_point3(imported)
+                .abs_diff_eq(expected, 1e-5)
+        );
+        assert!(
+            placed
+                .w_axis
+                .truncate()
+                .abs_diff_eq(glam::vec3(2.0, 0.0, 6.0), 1e-5)
+        );
+    }
+
+    #[test]
+    fn offscreen_render_views_returns_one_frame_per_camera() {
+        let Some((device, queue)) = test_device() else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        let mut renderer =
+            OffscreenRenderer::new(device, queue, 40, 30, wgpu::TextureFormat::Rgba8Unorm);
+        renderer.commands.push(
+            commands::DrawCommandBuilder::new(MeshType::Cube)
+                .with_color(1.0, 1.0, 1.0, 1.0)
+                .build(),
+        );
+
+        // Looking past the cube, so it is out of view
+        let mut away = PanOrbitCamera::default();
+        away.target = glam::vec3(100.0, 0.0, 0.0);
+        away.update();
+        let frames = renderer.render_views(&[PanOrbitCamera::default(), away]);
+
+        assert_eq!(frames.len(), 2);
+        for frame in &frames {
+            assert_eq!(frame.len(), 40 * 30 * 4);
+        }
+        let pixel = |frame: &[u8], x: usize, y: usize| {
+            let i = (y * 40 + x) * 4;
+            frame[i..i + 4].to_vec()
+        };
+        assert_ne!(pixel(&frames[0], 20, 15), pixel(&frames[0], 0, 0));
+        assert_eq!(pixel(&frames[1], 20, 15), pixel(&frames[1], 0, 0));
+        assert_eq!(renderer.commands.len(), 1);
+    }
+
+    #[test]
+    fn straight_tube_is_an_untwisted_cylinder() {
+        let segments = 8;
+        let radius = 0.5;
+        let points = [
+            glam::vec3(0.0, 0.0, 0.0),
+            glam::vec3(1.0, 0.0, 0.0),
+            glam::vec3(1.0, 0.0, 0.0),
+            glam::vec3(3.0, 0.0, 0.0),
+        ];
+        let (vertices, indices, edge_indices) =
+            mesh::tube_geometry(&points, radius, segments).unwrap();
+
+        // Duplicate point dropped: 3 rings, plus a center and ring per cap
+        assert_eq!(vertices.len(), 3 * segments + 2 * (segments + 1));
+        assert_eq!(indices.len(), (2 * 6 + 2 * 3) * segments);
+        assert!(edge_indices.iter().all(|&i| (i as usize) < vertices.len()));
+
+        let side = &vertices[..3 * segments];
+        for vertex in side {
+            let axis_point = glam::vec3(vertex.position.x, 0.0, 0.0);
+            let radial = vertex.position - axis_point;
+            assert!((radial.length() - radius).abs() < 1e-5);
+            assert!(vertex.normal.abs_diff_eq(radial / radius, 1e-5));
+            assert!(vertex.normal.dot(glam::Vec3::X).abs() < 1e-5);
+        }
+        // No twist: each ring repeats the first one's normals
+        for ring in side.chunks(segments).skip(1) {
+            for (vertex, first) in ring.iter().zip(&side[..segments]) {
+                assert!(vertex.normal.abs_diff_eq(first.normal, 1e-5));
+            }
+        }
+
+        // Side triangles face outward
+        for tri in indices[..2 * 6 * segments].chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|k| vertices[tri[k] as usize].position);
+            let face_normal = (b - a).cross(c - a);
+            let centroid = (a + b + c) / 3.0;
+            let outward = centroid - glam::vec3(centroid.x, 0.0, 0.0);
+            assert!(face_normal.dot(outward) > 0.0);
+        }
+
+        // Degenerate paths are rejected rather than panicking
+        let point = glam::Vec3::ONE;
+        assert!(mesh::tube_geometry(&[point, point], radius, segments).is_none());
+        assert!(mesh::tube_geometry(&[], radius, segments).is_none());
+
+        // Doubling back has no tangent at the turn, but still makes a tube
+        let there_and_back = [glam::Vec3::ZERO, glam::Vec3::X, glam::Vec3::ZERO];
+        let (vertices, ..) = mesh::tube_geometry(&there_and_back, radius, segments).unwrap();
+        assert!(
+            vertices
+                .iter()
+                .all(|v| v.position.is_finite() && v.normal.is_finite())
+        );
+    }
+
+    #[test]
+    fn raycast_hits_cube_face_center() {
+        use super::primitives::{CUBE_INDICES, CUBE_VERTICES};
+
+        let model = glam::Mat4::from_translation(glam::vec3(2.0, 0.0, 0.0));
+        let origin = glam::vec3(2.0, 0.0, 5.0);
+        let t = mesh::raycast_triangles(CUBE_VERTICES, CUBE_INDICES, origin, -glam::Vec3::Z, model);
+        assert_eq!(t, Some(4.0));
+
+        let miss =
+            mesh::raycast_triangles(CUBE_VERTICES, CUBE_INDICES, origin, glam::Vec3::Z, model);
+        assert_eq!(miss, None);
+    }
+
+    #[test]
+    fn mesh_bounds_enclose_vertices() {
+        let Some((device, _queue)) = test_device() else {
+            return;
+        };
+        let cube = mesh::Mesh::new_cube(&device);
+        assert_eq!(cube.bounds(), (glam::Vec3::splat(-1.0), glam::Vec3::ONE));
+
+        let vertices = [[0.0, 0.0, 0.0], [2.0, -1.0, 0.5], [-0.5, 3.0, 1.0]].map(|p| Vertex {
+            position: p.into(),
+            ..Default::default()
+        });
+        let triangle = mesh::Mesh::from_data(&device, &vertices, &[0, 1, 2], &[0, 1, 1, 2, 2, 0]);
+        assert_eq!(
+            triangle.bounds(),
+            (glam::vec3(-0.5, -1.0, 0.0), glam::vec3(2.0, 3.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn screen_rays_pass_through_the_clicked_point() {
+        let mut camera = PanOrbitCamera::default();
+        let viewport = glam::uvec2(800, 600);
+        camera.update_aspect(viewport);
+
+        let (origin, dir) = camera.screen_ray(glam::vec2(400.0, 300.0), viewport);
+        assert!(dir.abs_diff_eq(glam::Vec3::Y, 1e-4));
+        assert!(origin.x.abs() < 1e-3 && origin.z.abs() < 1e-3);
+
+        // The top-left corner ray heads up and to the left of the target
+        let (_, corner) = camera.screen_ray(glam::Vec2::ZERO, viewport);
+        assert!(corner.x < 0.0 && corner.z > 0.0);
+    }
+
+    #[test]
+    fn small_commands_are_swapped_for_bounding_cubes() {
+        let viewport = glam::uvec2(800, 600);
+        let mut camera = PanOrbitCamera::default();
+        camera.update_aspect(viewport);
+        let view_proj = *camera.proj_matrix() * *camera.view_matrix();
+        let bounds = HashMap::from([(MeshType::Sphere, (glam::Vec3::NEG_ONE, glam::Vec3::ONE))]);
+
+        let sphere = |position: glam::Vec3, scale: f32| {
+            commands::DrawCommandBuilder::new(MeshType::Sphere)
+                .with_position(position)
+                .with_scale(scale)
+                .build()
+        };
+        let mut commands = [sphere(glam::Vec3::X, 0.01), sphere(glam::Vec3::ZERO, 1.0)];
+        renderer::apply_proxies(&mut commands, &bounds, view_proj, viewport.as_vec2(), 4.0);
+
+        assert_eq!(commands[0].mesh_type, MeshType::Cube);
+        let expected = glam::Mat4::from_translation(glam::Vec3::X)
+            * glam::Mat4::from_scale(glam::Vec3::splat(0.01));
+        assert!(
+            commands[0]
+                .instance
+                .model_matrix
+                .abs_diff_eq(expected, 1e-6)
+        );
+        assert_eq!(commands[1].mesh_type, MeshType::Sphere);
+        assert_eq!(commands[1].instance.model_matrix, glam::Mat4::IDENTITY);
+    }
+
+    #[test]
+    fn stale_handles_stay_invalid_after_slot_reuse_and_clear() {
+        let mut retained = commands::RetainedCommands::default();
+        let cube = commands::DrawCommandBuilder::new(MeshType::Cube).build();
+        let sphere = commands::DrawCommandBuilder::new(MeshType::Sphere).build();
+
+        let removed = retained.insert(cube.clone());
+        assert!(retained.remove(removed).is_some());
+        let reused = retained.insert(sphere.clone());
+        assert_ne!(removed, reused);
+        assert!(retained.get(removed).is_none());
+        assert!(retained.get_mut(removed).is_none());
+        assert!(retained.remove(removed).is_none());
+        assert_eq!(
+            retained.get(reused).map(|cmd| cmd.mesh_type),
+            Some(MeshType::Sphere)
+        );
+
+        retained.clear();
+        assert!(retained.is_empty());
+        let after_clear = retained.insert(cube);
+        assert!(retained.get(reused).is_none());
+        assert!(retained.remove(reused).is_none());
+        assert_eq!(retained.len(), 1);
+        assert!(retained.get(after_clear).is_some());
+    }
+
+    #[test]
+    fn retained_revision_changes_only_on_edits() {
+        let mut retained = commands::RetainedCommands::default();
+        let command = commands::DrawCommandBuilder::new(MeshType::Cube).build();
+        let mut last = retained.revision();
+        let mut changed = |retained: &commands::RetainedCommands| {
+            let revision = retained.revision();
+            std::mem::replace(&mut last, revision) != revision
+        };
+
+        let handle = retained.insert(command.clone());
+        assert!(changed(&retained));
+        let _ = retained.get(handle);
+        let _ = retained.iter().count();
+        assert!(!changed(&retained));
+        retained.get_mut(handle).unwrap().instance.color = glam::Vec4::ONE;
+        assert!(changed(&retained));
+        retained.remove(handle);
+        assert!(changed(&retained));
+        // Stale handles change nothing
+        assert!(retained.get_mut(handle).is_none() && retained.remove(handle).is_none());
+        assert!(!changed(&retained));
+        retained.insert(command);
+        retained.clear();
+        assert!(changed(&retained));
+    }
+
+    #[test]
+    fn hex_colors_decode_srgb_channels_and_keep_alpha() {
+        let command = commands::DrawCommandBuilder::default()
+            .with_color_hex(0xff008080)
+            .build();
+        let [r, g, b, a] = command.instance.color.to_array();
+        assert_eq!((r, g), (1.0, 0.0));
+        // sRGB 0x80 is about 21.6% linear
+        assert!((b - 0.2159).abs() < 1e-3);
+        assert!((a - 128.0 / 255.0).abs() < 1e-6);
+
+        let red = commands::DrawCommandBuilder::default().red().build();
+        assert_eq!(red.instance.color, color::from_hex(0xff0000ff));
+
+        let srgb_bytes = commands::DrawCommandBuilder::default()
+            .with_color_srgb_u8(0xff, 0x00, 0x80, 0x80)
+            .build();
+        assert_eq!(srgb_bytes.instance.color, command.instance.color);
+        let linear_bytes = commands::DrawCommandBuilder::default()
+            .with_color_u8(0xff, 0x00, 0x80, 0x80)
+            .build();
+        assert_eq!(
+            linear_bytes.instance.color,
+            glam::vec4(1.0, 0.0, 128.0 / 255.0, 128.0 / 255.0)
+        );
+    }
+
+    #[test]
+    fn colormap_endpoints() {
+        let sample = |map, t| color::sample_colormap(map, t);
+
+        assert_eq!(
+            sample(Colormap::Grayscale, 0.0),
+            glam::vec4(0.0, 0.0, 0.0, 1.0)
+        );
+        assert_eq!(sample(Colormap::Grayscale, 1.0), glam::Vec4::ONE);
+
+        // #440154 and #fde725 in linear RGB
+        let start = glam::vec4(0.05781, 0.0003, 0.08866, 1.0);
+        let end = glam::vec4(0.98225, 0.7991, 0.0185, 1.0);
+        assert!(sample(Colormap::Viridis, 0.0).abs_diff_eq(start, 1e-4));
+        assert!(sample(Colormap::Viridis, 1.0).abs_diff_eq(end, 1e-4));
+
+        // Out of range values clamp to the endpoints
+        assert_eq!(
+            sample(Colormap::Viridis, -1.0),
+            sample(Colormap::Viridis, 0.0)
+        );
+        assert_eq!(sample(Colormap::Turbo, 2.0), sample(Colormap::Turbo, 1.0));
+    }
+
+    #[test]
+    fn translucent_commands_blend_back_to_front_in_any_order() {
+        let Some((device, queue)) = test_device() else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        let mut renderer =
+            OffscreenRenderer::new(device, queue, 40, 30, wgpu::TextureFormat::Rgba8Unorm);
+
+        // Thin boxes facing the default camera, which looks along +Y
+        let quad = |y: f32, r: f32, b: f32| {
+            commands::DrawCommandBuilder::new(MeshType::Cube)
+                .with_model_matrix(glam::Mat4::from_scale_rotation_translation(
+                    glam::vec3(4.0, 0.01, 4.0),
+                    glam::Quat::IDENTITY,
+                    glam::vec3(0.0, y, 0.0),
+                ))
+                .with_color(r, 0.0, b, 0.5)
+                .build()
+        };
+        let far_red = quad(1.0, 1.0, 0.0);
+        let near_blue = quad(-1.0, 0.0, 1.0);
+
+        let center = (15 * 40 + 20) * 4;
+        let mut pixels = Vec::new();
+        for order in [[&far_red, &near_blue], [&near_blue, &far_red]] {
+            renderer.commands = order.into_iter().cloned().collect();
+            let frames = renderer.render_views(&[PanOrbitCamera::default()]);
+            pixels.push(frames[0][center..center + 4].to_vec());
+        }
+
+        assert_eq!(pixels[0], pixels[1]);
+        // The nearer quad is blended last, so it dominates
+        assert!(pixels[0][2] > pixels[0][0], "{:?}", pixels[0]);
+
+        // Premultiplied output composites to the same color
+        renderer.set_blend_mode(BlendMode::Premultiplied);
+        let frames = renderer.render_views(&[PanOrbitCamera::default()]);
+        let premultiplied = &frames[0][center..center + 4];
+        for channel in 0..3 {
+            assert!(premultiplied[channel].abs_diff(pixels[0][channel]) <= 2);
+        }
+    }
+
+    #[test]
+    fn roll_rotates_screen_up_about_view_direction() {
+        let screen_up = |camera: &PanOrbitCamera| camera.view_matrix().inverse().y_axis.truncate();
+        let mut camera = PanOrbitCamera::default();
+        assert!(screen_up(&camera).abs_diff_eq(glam::Vec3::Z, 1e-5));
+
+        // The default camera looks along +Y, so a quarter roll tips up to +X
+        camera.roll(std::f32::consts::FRAC_PI_2);
+        assert!(screen_up(&camera).abs_diff_eq(glam::Vec3::X, 1e-5));
+        camera.orbit(glam::vec2(0.0, 10.0));
+        camera.orbit(glam::vec2(0.0, -10.0));
+        assert!(screen_up(&camera).abs_diff_eq(glam::Vec3::X, 1e-4));
+
+        camera.reset_roll();
+        assert!(screen_up(&camera).abs_diff_eq(glam::Vec3::Z, 1e-4));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn draw_commands_round_trip_through_json() {
+        let commands = vec![
+            commands::DrawCommandBuilder::new(MeshType::Custom(7))
+                .with_position(glam::vec3(0.1, -2.5, 1.0e-7))
+                .with_rotation_euler(glam::vec3(0.3, 1.1, -0.7))
+                .with_scale(0.123)
+                .with_color(0.2, 0.4, 0.6, 0.8)
+                .with_outline_style(1.02, glam::Vec4::X)
+                .build(),
+            commands::DrawCommandBuilder::new(MeshType::Sphere).build(),
+        ];
+        let json = serde_json::to_string(&commands).unwrap();
+        let loaded: Vec<commands::DrawCommand> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded.len(), commands.len());
+        for (loaded, command) in loaded.iter().zip(&commands) {
+            assert_eq!(loaded.mesh_type, command.mesh_type);
+            assert_eq!(
+                bytemuck::bytes_of(&loaded.instance),
+                bytemuck::bytes_of(&command.instance)
+            );
+            assert_eq!(loaded.outline, command.outline);
+            assert_eq!(loaded.outline_style, command.outline_style);
+        }
+    }
+
+    #[test]
+    fn scripted_camera_moves_in_world_units() {
+        let eye = |camera: &PanOrbitCamera| camera.view_matrix().inverse().w_axis.truncate();
+        let mut camera = PanOrbitCamera::default();
+        assert!(eye(&camera).abs_diff_eq(glam::vec3(0.0, -10.0, 0.0), 1e-4));
+
+        camera.orbit_by(std::f32::consts::FRAC_PI_2, 0.0);
+        assert!(eye(&camera).abs_diff_eq(glam::vec3(10.0, 0.0, 0.0), 1e-4));
+
+        // Looking along -X now, so the view's right is +Y
+        camera.pan_by(1.0, 2.0);
+        assert!(camera.target.abs_diff_eq(glam::vec3(0.0, 1.0, 2.0), 1e-5));
+
+        camera.zoom_by(4.0);
+        assert!((camera.distance - 6.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn auto_orbit_turns_by_the_ticked_time() {
+        let eye = |camera: &PanOrbitCamera| camera.view_matrix().inverse().w_axis.truncate();
+        let mut camera = PanOrbitCamera::default();
+        camera.auto_orbit = Some(std::f32::consts::FRAC_PI_4);
+        for _ in 0..4 {
+            camera.tick(std::time::Duration::from_millis(500));
+        }
+        assert!(eye(&camera).abs_diff_eq(glam::vec3(10.0, 0.0, 0.0), 1e-4));
+    }
+
+    #[test]
+    fn orbit_pitch_stops_short_of_the_poles() {
+        let screen_up = |camera: &PanOrbitCamera| camera.view_matrix().inverse().y_axis.truncate();
+        let mut camera = PanOrbitCamera::default();
+        for _ in 0..20 {
+            camera.orbit(glam::vec2(0.0, 100.0));
+        }
+        assert!(screen_up(&camera).z > 0.0);
+        let forward = camera.orientation * glam::Vec3::Y;
+        assert!(forward.z.asin() <= 89.5_f32.to_radians());
+
+        camera.clamp_pitch = false;
+        camera.orbit(glam::vec2(0.0, 100.0));
+        assert!(screen_up(&camera).z < 0.0);
+    }
+
+    #[test]
+    fn orbit_and_roll_follow_world_up() {
+        let screen_up = |camera: &PanOrbitCamera| camera.view_matrix().inverse().y_axis.truncate();
+        let eye = |camera: &PanOrbitCamera| camera.view_matrix().inverse().w_axis.truncate();
+
+        for world_up in [glam::Vec3::Z, glam::Vec3::Y] {
+            let mut camera = PanOrbitCamera::default();
+            camera.set_world_up(world_up);
+            assert!(screen_up(&camera).abs_diff_eq(world_up, 1e-5));
+            if world_up == glam::Vec3::Y {
+                assert!(eye(&camera).abs_diff_eq(glam::vec3(0.0, 0.0, 10.0), 1e-4));
+            }
+
+            // Orbiting sideways circles about world up at a constant height
+            let height = eye(&camera).dot(world_up);
+            camera.orbit(glam::vec2(200.0, 0.0));
+            assert!((eye(&camera).dot(world_up) - height).abs() < 1e-4);
+            assert!(screen_up(&camera).abs_diff_eq(world_up, 1e-4));
+
+            camera.orbit(glam::vec2(0.0, 50.0));
+            camera.roll(0.3);
+            camera.reset_roll();
+            let forward = (camera.target - eye(&camera)).normalize();
+            assert!(screen_up(&camera).dot(forward.cross(world_up)).abs() < 1e-4);
+            assert!(screen_up(&camera).dot(world_up) > 0.0);
+        }
+    }
+
+    #[test]
+    fn mesh_from_data_builds_a_single_triangle() {
+        let Some((device, _queue)) = test_device() else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        let normal = glam::Vec3::Z;
+        let vertices = [
+            Vertex::new(glam::vec3(0.0, 0.0, 0.0), glam::Vec3::ONE, normal),
+            Vertex::new(glam::vec3(1.0, 0.0, 0.0), glam::Vec3::ONE, normal),
+            Vertex::new(glam::vec3(0.0, 1.0, 0.0), glam::Vec3::ONE, normal),
+        ];
+        let mesh = mesh::Mesh::from_data(&device, &vertices, &[0, 1, 2], &[0, 1, 1, 2, 2, 0]);
+
+        assert!(matches!(mesh.mesh_type(), MeshType::Custom(_)));
+        assert_eq!(mesh.vertices.len(), 3);
+        assert_eq!(mesh.indices, [0, 1, 2]);
+        assert_eq!(mesh.edge_indices.len(), 6);
+        assert_eq!(
+            mesh.buffers.vertex_buffer.size(),
+            (3 * std::mem::size_of::<Vertex>()) as u64
+        );
+        assert_eq!(
+            mesh.buffers.instance_capacity,
+            mesh::DEFAULT_INSTANCE_CAPACITY
+        );
+        assert_eq!(
+            mesh.raycast(
+                glam::vec3(0.25, 0.25, 1.0),
+                -glam::Vec3::Z,
+                glam::Mat4::IDENTITY
+            ),
+            Some(1.0)
+        );
+    }
+
+    #[test]
+    fn sphere_with_more_vertices_than_u16_indices_renders() {
+        let Some((device, queue)) = test_device() else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        let sphere = mesh::Mesh::new_sphere(&device, 200);
+        assert!(sphere.vertices.len() > u16::MAX as usize + 1);
+        let max_index = *sphere.indices.iter().max().unwrap();
+        assert!(max_index > u16::MAX as u32);
+        assert!((max_index as usize) < sphere.vertices.len());
+
+        let mut renderer =
+            OffscreenRenderer::new(device, queue, 40, 30, wgpu::TextureFormat::Rgba8Unorm);
+        renderer.meshes.insert(MeshType::Sphere, sphere);
+        renderer
+            .commands
+            .push(commands::DrawCommandBuilder::new(MeshType::Sphere).build());
+        let frames = renderer.render_views(&[PanOrbitCamera::default()]);
+
+        let center = (15 * 40 + 20) * 4;
+        assert!(
+            frames[0][center] > 64,
+            "{:?}",
+            &frames[0][center..center + 4]
+        );
+    }
+
+    #[test]
+    fn sphere_edges_are_unique_and_in_range() {
+        let Some((device, _queue)) = test_device() else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        for divisions in [0, 1, 2, 3, 4, 10] {
+            for edge_stride in [1, 2, 3] {
+                let full = mesh::Mesh::new_sphere(&device, divisions).edge_indices;
+                let sphere = mesh::Mesh::new_sphere(&device, divisions)
+                    .with_edge_stride(&device, edge_stride);
+                assert_eq!(edge_stride == 1, sphere.edge_indices == full);
+                let vertex_count = sphere.vertices.len() as u32;
+                assert!(sphere.indices.iter().all(|&index| index < vertex_count));
+                assert!(
+                    sphere
+                        .edge_indices
+                        .iter()
+                        .all(|&index| index < vertex_count)
+                );
+                assert_eq!(sphere.edge_indices.len() % 2, 0);
+
+                let mut edges = std::collections::HashSet::new();
+                for edge in sphere.edge_indices.chunks_exact(2) {
+                    assert_ne!(edge[0], edge[1]);
+                    assert!(
+                        edges.insert((edge[0].min(edge[1]), edge[0].max(edge[1]))),
+                        "duplicate edge {edge:?} with {divisions} divisions"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn instance_from_position_rotation_scales_whole_basis() {
+        let rotation = glam::Mat3::from_rotation_z(std::f32::consts::FRAC_PI_2);
+        let instance = Instance::from_position_rotation(
+            glam::vec3(1.0, 2.0, 3.0),
+            rotation,
+            2.0,
+            glam::Vec4::ONE,
+        );
+
+        let expected = glam::Mat4::from_cols(
+            glam::vec4(0.0, 2.0, 0.0, 0.0),
+            glam::vec4(-2.0, 0.0, 0.0, 0.0),
+            glam::vec4(0.0, 0.0, 2.0, 0.0),
+            glam::vec4(1.0, 2.0, 3.0, 1.0),
+        );
+        assert!(
+            instance.model_matrix.abs_diff_eq(expected, 1e-6),
+            "{}",
+            instance.model_matrix
+        );
+    }
+
+    #[test]
+    fn pick_returns_the_retained_command_at_the_pixel() {
+        let Some((device, queue)) = test_device() else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        let shader = renderer::create_default_shader(&device);
+        let target = renderer::create_pick_target(&device, &shader);
+        let meshes = [(MeshType::Cube, mesh::Mesh::new_cube(&device))].into();
+        // The 1x1 target sees the middle of the view, looking down -Z
+        let view_proj = glam::Mat4::perspective_rh(1.0, 1.0, 0.1, 100.0)
+            * glam::Mat4::look_at_rh(glam::vec3(0.0, 0.0, 5.0), glam::Vec3::ZERO, glam::Vec3::Y);
+        let cube_at = |x: f32, z: f32| {
+            commands::DrawCommandBuilder::new(MeshType::Cube)
+                .with_position(glam::vec3(x, 0.0, z))
+                .build()
+        };
+        let pick = |immediate: &[commands::DrawCommand], retained: &commands::RetainedCommands| {
+            let (instances, handles) =
+                renderer::pick_instances(immediate, retained, glam::Mat3::IDENTITY);
+            let id = renderer::render_pick(
+                &device,
+                &queue,
+                &target,
+                &meshes,
+                renderer::Uniforms::new(view_proj),
+                &instances,
+            );
+            (id as usize).checked_sub(1).map(|index| handles[index])
+        };
+
+        let mut retained = commands::RetainedCommands::default();
+        retained.insert(cube_at(3.0, 0.0));
+        let centered = retained.insert(cube_at(0.0, 0.0));
+        let behind = retained.insert(cube_at(0.0, -3.0));
+        assert_eq!(pick(&[], &retained), Some(centered));
+
+        // Immediate commands occlude but can't be picked
+        assert_eq!(pick(&[cube_at(0.0, 2.0)], &retained), None);
+
+        retained.remove(centered);
+        assert_eq!(pick(&[], &retained), Some(behind));
+    }
+
+    #[test]
+    fn grid_geometry_stays_bounded_for_tiny_or_invalid_spacing() {
+        let max_lines = 2 * mesh::MAX_GRID_HALF_LINES as usize + 1;
+        for spacing in [0.0, -1.0, 1e-30, f32::MIN_POSITIVE] {
+            let (vertices, line_indices, axis_indices) = mesh::grid_geometry(10.0, spacing);
+            assert_eq!(vertices.len(), 4 * max_lines);
+            assert_eq!(line_indices.len() + axis_indices.len(), 4 * max_lines);
+        }
+
+        let (vertices, line_indices, axis_indices) = mesh::grid_geometry(10.0, 1.0);
+        assert_eq!(vertices.len(), 4 * 21);
+        assert_eq!((line_indices.len(), axis_indices.len()), (4 * 20, 4));
+
+        for (half_extent, spacing) in [
+            (0.0, 1.0),
+            (-1.0, 1.0),
+            (f32::INFINITY, 1.0),
+            (10.0, f32::NAN),
+        ] {
+            let (vertices, _, axis_indices) = mesh::grid_geometry(half_extent, spacing);
+            assert!(vertices.is_empty() && axis_indices.is_empty());
+        }
+    }
+
+    #[test]
+    fn instance_batch_insert_update_remove() {
+        let Some((device, queue)) = test_device() else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        let instances = |count: usize| vec![bytemuck::Zeroable::zeroed(); count];
+        let mut batches = commands::RetainedCommands::<commands::InstanceBatch>::default();
+
+        let handle = batches.insert(commands::InstanceBatch::new(
+            &device,
+            &queue,
+            MeshType::Cube,
+            instances(4),
+        ));
+        let batch = batches.get(handle).unwrap();
+        assert_eq!(batch.uploaded_count(), 4);
+        let buffer_size = batch.buffer.size();
+
+        // Shrinking reuses the buffer, growing replaces it
+        let revision = batches.revision();
+        batches
+            .get_mut(handle)
+            .unwrap()
+            .set_instances(&device, &queue, instances(2));
+        assert!(batches.revision() > revision);
+        let batch = batches.get(handle).unwrap();
+        assert_eq!(
+            (batch.uploaded_count(), batch.buffer.size()),
+            (2, buffer_size)
+        );
+        batches
+            .get_mut(handle)
+            .unwrap()
+            .set_instances(&device, &queue, instances(9));
+        let batch = batches.get(handle).unwrap();
+        assert_eq!(batch.uploaded_count(), 9);
+        assert!(batch.buffer.size() > buffer_size);
+
+        let removed = batches.remove(handle).unwrap();
+        assert_eq!(removed.instances.len(), 9);
+        assert!(batches.get(handle).is_none());
+        assert!(batches.is_empty());
+
+        let empty = commands::InstanceBatch::new(&device, &queue, MeshType::Cube, Vec::new());
+        assert_eq!(empty.uploaded_count(), 0);
+    }
+
+    #[test]
+    fn bgra_readback_comes_back_as_rgba() {
+        let Some((device, queue)) = test_device() else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        // The readback `capture_frame` uses on BGRA surfaces
+        let mut renderer =
+            OffscreenRenderer::new(device, queue, 4, 4, wgpu::TextureFormat::Bgra8Unorm);
+        renderer.clear_color = wgpu::Color::RED;
+        renderer.render_to_texture();
+        let pixels = renderer.read_pixels();
+        assert_eq!(pixels.len(), 4 * 4 * 4);
+        for pixel in pixels.chunks_exact(4) {
+            assert_eq!(pixel, [255, 0, 0, 255]);
+        }
+    }
+
+    #[cfg(feature = "obj")]
+    #[test]
+    fn from_obj_with_applies_the_import_transform() {
+        let Some((device, _queue)) = test_device() else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        let path = std::env::temp_dir().join("raiden_y_up_triangle.obj");
+        std::fs::write(&path, "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n").unwrap();
+        let transform = ImportTransform {
+            up_axis: UpAxis::Y,
+            ..Default::default()
+        };
+        let mesh = mesh::Mesh::from_obj_with(&device, &path, transform).unwrap();
+        let as_is = mesh::Mesh::from_obj(&device, &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // The Y-up apex points up +Z once imported
+        assert!(mesh.vertices[2].position.abs_diff_eq(glam::Vec3::Z, 1e-6));
+        assert!(as_is.vertices[2].position.abs_diff_eq(glam::Vec3::Y, 1e-6));
+    }
+
+    #[test]
+    fn with_transform_keeps_shear_and_nonuniform_scale() {
+        let transform = glam::Mat4::from_cols(
+            glam::vec4(2.0, 0.0, 0.0, 0.0),
+            glam::vec4(0.5, 1.0, 0.0, 0.0),
+            glam::vec4(0.0, 0.0, 3.0, 0.0),
+            glam::vec4(1.0, 2.0, 3.0, 1.0),
+        );
+        let command = commands::DrawCommandBuilder::new(MeshType::Cube)
+            .with_position(glam::Vec3::splat(9.0))
+            .with_scale(4.0)
+            .with_transform(transform)
+            .build();
+        assert_eq!(command.instance.model_matrix, transform);
+    }
 }
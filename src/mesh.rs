@@ -1,16 +1,32 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::atomic::{AtomicU32, Ordering};
 use wgpu::util::DeviceExt;
 
+#[cfg(feature = "gltf")]
+use super::commands::DrawCommand;
+#[cfg(any(feature = "obj", feature = "gltf"))]
+use super::error::RaidenError;
+#[cfg(any(feature = "obj", feature = "gltf"))]
+use super::import;
 use super::primitives;
-use super::renderer::{Instance, Renderer};
+use super::renderer::Instance;
 
 pub const DEFAULT_INSTANCE_CAPACITY: usize = 100;
 
+/// Number of instances that fit in one buffer of at most `max_buffer_size`
+/// bytes. Draws with more instances are split across several buffers.
+pub fn max_instances_per_buffer(max_buffer_size: u64) -> usize {
+    (max_buffer_size / std::mem::size_of::<Instance>() as u64).max(1) as usize
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
-    position: glam::Vec3,
-    color: glam::Vec3,
-    normal: glam::Vec3,
+    pub position: glam::Vec3,
+    pub color: glam::Vec3,
+    pub normal: glam::Vec3,
+    pub uv: glam::Vec2,
 }
 
 impl Default for Vertex {
@@ -19,13 +35,18 @@ impl Default for Vertex {
             position: glam::Vec3::ZERO,
             color: glam::Vec3::ONE,
             normal: glam::Vec3::ZERO,
+            uv: glam::Vec2::ZERO,
         }
     }
 }
 
 impl Vertex {
-    const ATTRIBS: [wgpu::VertexAttribute; 3] =
-        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3];
+    pub(crate) const ATTRIBS: [wgpu::VertexAttribute; 4] = wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Float32x3,
+        2 => Float32x3,
+        3 => Float32x2
+    ];
     pub fn desc() -> wgpu::VertexBufferLayout<'static> {
         use std::mem;
 
@@ -35,25 +56,58 @@ impl Vertex {
             attributes: &Self::ATTRIBS,
         }
     }
+    /// UVs default to a planar projection of the position onto the XY plane
     pub const fn new(position: glam::Vec3, color: glam::Vec3, normal: glam::Vec3) -> Self {
         Self {
             position,
             color,
             normal,
+            uv: glam::Vec2::new(0.5 + 0.5 * position.x, 0.5 - 0.5 * position.y),
         }
     }
+
+    pub const fn with_uv(self, uv: glam::Vec2) -> Self {
+        Self { uv, ..self }
+    }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MeshType {
     Triangle,
     Cube,
     Tetrahedron,
     Sphere,
+    Cylinder,
+    Cone,
+    Torus,
+    Plane,
+    Icosphere,
+    Capsule,
+    Arrow,
+    /// Unit quad the renderer turns to face the camera every frame
+    Billboard,
+    /// Geometry built at runtime, keyed by an id from [`MeshType::next_custom`]
+    Custom(u32),
+}
+
+impl MeshType {
+    /// A `Custom` mesh type with a process-wide unique id
+    pub fn next_custom() -> Self {
+        static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+        MeshType::Custom(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Debug label for a GPU resource belonging to `mesh`, e.g.
+/// "Instance Buffer (Cube)", so captures and validation errors name the mesh.
+pub fn label(prefix: &str, mesh: MeshType) -> String {
+    format!("{prefix} ({mesh:?})")
 }
 
 #[derive(Debug)]
 pub struct MeshBuffers {
+    pub mesh_type: MeshType,
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
     pub instance_buffer: wgpu::Buffer,
@@ -61,30 +115,33 @@ pub struct MeshBuffers {
     pub edge_index_buffer: wgpu::Buffer,
     pub edge_instance_buffer: wgpu::Buffer,
     pub edge_instance_capacity: usize,
+    /// Bumped whenever the instance buffer is written or replaced, so a
+    /// caller can tell its last upload is still there
+    instance_uploads: u64,
 }
 
 impl MeshBuffers {
     pub fn new(
-        label_prefix: &str,
+        mesh_type: MeshType,
         device: &wgpu::Device,
         vertices: &[Vertex],
-        indices: &[u16],
-        edge_indices: &[u16],
+        indices: &[u32],
+        edge_indices: &[u32],
     ) -> Self {
         // Create vertex buffer
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some(&format!("{label_prefix} Vertex Buffer")),
+            label: Some(&label("Vertex Buffer", mesh_type)),
             contents: bytemuck::cast_slice(vertices),
             usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         });
         let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some(&format!("{label_prefix} Index Buffer")),
+            label: Some(&label("Index Buffer", mesh_type)),
             contents: bytemuck::cast_slice(indices),
             usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
         });
         let instance_capacity = DEFAULT_INSTANCE_CAPACITY;
         let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some(&format!("{label_prefix} Instance Buffer")),
+            label: Some(&label("Instance Buffer", mesh_type)),
             size: (instance_capacity * std::mem::size_of::<Instance>()) as wgpu::BufferAddress,
             usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
@@ -92,18 +149,19 @@ impl MeshBuffers {
 
         // Edge buffers
         let edge_index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some(&format!("{label_prefix} Edge Index Buffer")),
+            label: Some(&label("Edge Index Buffer", mesh_type)),
             contents: bytemuck::cast_slice(edge_indices),
             usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
         });
         let edge_instance_capacity = DEFAULT_INSTANCE_CAPACITY;
         let edge_instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some(&format!("{label_prefix} Edge Instance Buffer")),
+            label: Some(&label("Edge Instance Buffer", mesh_type)),
             size: (edge_instance_capacity * std::mem::size_of::<Instance>()) as wgpu::BufferAddress,
             usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
         Self {
+            mesh_type,
             vertex_buffer,
             index_buffer,
             instance_capacity,
@@ -111,24 +169,47 @@ impl MeshBuffers {
             edge_index_buffer,
             edge_instance_capacity,
             edge_instance_buffer,
+            instance_uploads: 0,
         }
     }
 }
 
 pub struct Mesh {
     pub vertices: Vec<Vertex>,
-    pub indices: Vec<u16>,
-    pub edge_indices: Vec<u16>,
+    pub indices: Vec<u32>,
+    pub edge_indices: Vec<u32>,
+    /// Distance from the mesh origin to its farthest vertex, for culling
+    pub bounding_radius: f32,
+    bounds: (glam::Vec3, glam::Vec3),
     pub buffers: MeshBuffers,
 }
 
 impl Mesh {
+    /// Axis-aligned `(min, max)` of the vertices in model space, computed
+    /// when the mesh was built
+    pub fn bounds(&self) -> (glam::Vec3, glam::Vec3) {
+        self.bounds
+    }
+
+    pub fn mesh_type(&self) -> MeshType {
+        self.buffers.mesh_type
+    }
+
+    /// Changes whenever the instance buffer's contents may have, see
+    /// [`Self::upload_instances`]
+    pub fn instance_uploads(&self) -> u64 {
+        self.buffers.instance_uploads
+    }
+
     pub fn realloc_instance_buffer(&mut self, device: &wgpu::Device, new_capacity: usize) {
-        while self.buffers.instance_capacity < new_capacity {
+        let max_capacity = max_instances_per_buffer(device.limits().max_buffer_size);
+        while self.buffers.instance_capacity < new_capacity.min(max_capacity) {
             self.buffers.instance_capacity *= 2;
         }
+        self.buffers.instance_capacity = self.buffers.instance_capacity.min(max_capacity);
+        self.buffers.instance_uploads += 1;
         self.buffers.instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Mesh Edge Instance Buffer"),
+            label: Some(&label("Instance Buffer", self.buffers.mesh_type)),
             size: (self.buffers.instance_capacity * std::mem::size_of::<Instance>())
                 as wgpu::BufferAddress,
             usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
@@ -137,11 +218,13 @@ impl Mesh {
     }
 
     pub fn realloc_edge_instance_buffer(&mut self, device: &wgpu::Device, new_capacity: usize) {
-        while self.buffers.edge_instance_capacity < new_capacity {
+        let max_capacity = max_instances_per_buffer(device.limits().max_buffer_size);
+        while self.buffers.edge_instance_capacity < new_capacity.min(max_capacity) {
             self.buffers.edge_instance_capacity *= 2;
         }
+        self.buffers.edge_instance_capacity = self.buffers.edge_instance_capacity.min(max_capacity);
         self.buffers.edge_instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Mesh Edge Instance Buffer"),
+            label: Some(&label("Edge Instance Buffer", self.buffers.mesh_type)),
             size: (self.buffers.edge_instance_capacity * std::mem::size_of::<Instance>())
                 as wgpu::BufferAddress,
             usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
@@ -149,14 +232,225 @@ impl Mesh {
         });
     }
 
+    /// Shrink both instance buffers back to [`DEFAULT_INSTANCE_CAPACITY`],
+    /// releasing memory grown for a larger scene
+    pub fn reset_instance_capacity(&mut self, device: &wgpu::Device) {
+        self.buffers.instance_capacity = DEFAULT_INSTANCE_CAPACITY;
+        self.realloc_instance_buffer(device, DEFAULT_INSTANCE_CAPACITY);
+        self.buffers.edge_instance_capacity = DEFAULT_INSTANCE_CAPACITY;
+        self.realloc_edge_instance_buffer(device, DEFAULT_INSTANCE_CAPACITY);
+    }
+
+    /// Upload `instances` (growing the instance buffer if needed) and draw
+    /// them with the mesh's triangle indices
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        instances: &[Instance],
+    ) {
+        if instances.len() > self.buffers.instance_capacity {
+            self.realloc_instance_buffer(device, instances.len());
+        }
+        self.buffers.instance_uploads += 1;
+        render_pass.set_vertex_buffer(0, self.buffers.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(
+            self.buffers.index_buffer.slice(..),
+            wgpu::IndexFormat::Uint32,
+        );
+        draw_chunked(
+            device,
+            queue,
+            render_pass,
+            &self.buffers.instance_buffer,
+            self.buffers.instance_capacity,
+            self.indices.len() as u32,
+            instances,
+        );
+    }
+
+    /// Upload `instances` to the edge instance buffer and draw them with the
+    /// mesh's edge (line list) indices
+    pub fn draw_edges(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        instances: &[Instance],
+    ) {
+        if instances.len() > self.buffers.edge_instance_capacity {
+            self.realloc_edge_instance_buffer(device, instances.len());
+        }
+        render_pass.set_vertex_buffer(0, self.buffers.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(
+            self.buffers.edge_index_buffer.slice(..),
+            wgpu::IndexFormat::Uint32,
+        );
+        draw_chunked(
+            device,
+            queue,
+            render_pass,
+            &self.buffers.edge_instance_buffer,
+            self.buffers.edge_instance_capacity,
+            self.edge_indices.len() as u32,
+            instances,
+        );
+    }
+
+    /// Upload `instances` for [`Self::draw_instance_range`], growing the
+    /// instance buffer if needed. Uploads nothing and returns `false` when
+    /// they don't fit in one buffer. Bumps [`Self::instance_uploads`].
+    pub fn upload_instances(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        instances: &[Instance],
+    ) -> bool {
+        if instances.len() > self.buffers.instance_capacity {
+            self.realloc_instance_buffer(device, instances.len());
+        }
+        if instances.len() > self.buffers.instance_capacity {
+            return false;
+        }
+        if !instances.is_empty() {
+            queue.write_buffer(
+                &self.buffers.instance_buffer,
+                0,
+                bytemuck::cast_slice(instances),
+            );
+        }
+        self.buffers.instance_uploads += 1;
+        true
+    }
+
+    /// Draw triangles for `range` of the instances last uploaded by
+    /// [`Self::upload_instances`]
+    pub fn draw_instance_range(&self, render_pass: &mut wgpu::RenderPass<'_>, range: Range<u32>) {
+        self.draw_instance_range_from(render_pass, &self.buffers.instance_buffer, range);
+    }
+
+    /// Draw triangles for `range` of the instances in `instance_buffer`, e.g.
+    /// an [`crate::commands::InstanceBatch`] buffer
+    pub fn draw_instance_range_from(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        instance_buffer: &wgpu::Buffer,
+        range: Range<u32>,
+    ) {
+        if !range.is_empty() {
+            render_pass.set_vertex_buffer(0, self.buffers.vertex_buffer.slice(..));
+            render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+            render_pass.set_index_buffer(
+                self.buffers.index_buffer.slice(..),
+                wgpu::IndexFormat::Uint32,
+            );
+            render_pass.draw_indexed(0..self.indices.len() as u32, 0, range);
+        }
+    }
+
+    /// Draw triangles for the instances last uploaded by [`Self::draw`],
+    /// e.g. for a second pass over the same objects. Draws nothing and
+    /// returns `false` when that draw spilled past the instance buffer.
+    pub fn draw_reusing_instances(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        instance_count: usize,
+    ) -> bool {
+        if instance_count > self.buffers.instance_capacity {
+            return false;
+        }
+        self.draw_with_instance_buffer(
+            render_pass,
+            &self.buffers.index_buffer,
+            self.indices.len(),
+            0..instance_count as u32,
+        );
+        true
+    }
+
+    /// Draw edges for the instances last uploaded by [`Self::draw`], for
+    /// pipelines that derive the outline look in the shader. Draws nothing
+    /// and returns `false` when that draw spilled past the instance buffer.
+    pub fn draw_edges_reusing_instances(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        instance_count: usize,
+    ) -> bool {
+        if instance_count > self.buffers.instance_capacity {
+            return false;
+        }
+        self.draw_with_instance_buffer(
+            render_pass,
+            &self.buffers.edge_index_buffer,
+            self.edge_indices.len(),
+            0..instance_count as u32,
+        );
+        true
+    }
+
+    fn draw_with_instance_buffer(
+        &self,
+        render_pass: &mut wgpu::RenderPass<'_>,
+        index_buffer: &wgpu::Buffer,
+        index_count: usize,
+        instances: Range<u32>,
+    ) {
+        render_pass.set_vertex_buffer(0, self.buffers.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.buffers.instance_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..index_count as u32, 0, instances);
+    }
+
+    /// Nearest intersection of a world-space ray with this mesh drawn with
+    /// `model`, as the parameter `t` of `ray_origin + t * ray_dir` (the
+    /// distance when `ray_dir` is normalized). Both faces of each triangle
+    /// are hit.
+    pub fn raycast(
+        &self,
+        ray_origin: glam::Vec3,
+        ray_dir: glam::Vec3,
+        model: glam::Mat4,
+    ) -> Option<f32> {
+        raycast_triangles(&self.vertices, &self.indices, ray_origin, ray_dir, model)
+    }
+
     pub fn new_cube(device: &wgpu::Device) -> Self {
         use primitives::{CUBE_EDGES, CUBE_INDICES, CUBE_VERTICES};
-        let buffers = MeshBuffers::new("Cube", device, CUBE_VERTICES, CUBE_INDICES, CUBE_EDGES);
+        let buffers = MeshBuffers::new(
+            MeshType::Cube,
+            device,
+            CUBE_VERTICES,
+            CUBE_INDICES,
+            CUBE_EDGES,
+        );
 
         Self {
             vertices: CUBE_VERTICES.to_vec(),
             indices: CUBE_INDICES.to_vec(),
             edge_indices: CUBE_EDGES.to_vec(),
+            bounding_radius: bounding_radius(CUBE_VERTICES),
+            bounds: vertex_bounds(CUBE_VERTICES),
+            buffers,
+        }
+    }
+
+    pub fn new_triangle(device: &wgpu::Device) -> Self {
+        use primitives::{TRIANGLE_EDGES, TRIANGLE_INDICES, TRIANGLE_VERTICES};
+        let buffers = MeshBuffers::new(
+            MeshType::Triangle,
+            device,
+            TRIANGLE_VERTICES,
+            TRIANGLE_INDICES,
+            TRIANGLE_EDGES,
+        );
+
+        Self {
+            vertices: TRIANGLE_VERTICES.to_vec(),
+            indices: TRIANGLE_INDICES.to_vec(),
+            edge_indices: TRIANGLE_EDGES.to_vec(),
+            bounding_radius: bounding_radius(TRIANGLE_VERTICES),
+            bounds: vertex_bounds(TRIANGLE_VERTICES),
             buffers,
         }
     }
@@ -166,7 +460,7 @@ impl Mesh {
         const N_INDICES: usize = 12;
 
         #[rustfmt::skip]
-        let edge_indices: [u16; N_INDICES] = [
+        let edge_indices: [u32; N_INDICES] = [
             0, 1,
             1, 2,
             2, 0,
@@ -189,9 +483,12 @@ impl Mesh {
         vertices[2].position = glam::vec3(-d, c, b);
         // Top vertex aligned with z-axis
         vertices[3].position = glam::vec3(0.0, 0.0, e);
+        for vertex in vertices.iter_mut() {
+            vertex.uv = glam::vec2(0.5 + 0.5 * vertex.position.x, 0.5 - 0.5 * vertex.position.y);
+        }
 
         #[rustfmt::skip]
-        let indices: [u16; N_INDICES] = [
+        let indices: [u32; N_INDICES] = [
             0, 1, 2,
             0, 2, 3,
             2, 1, 3,
@@ -219,27 +516,36 @@ impl Mesh {
             vertices[v].normal = v_norm.normalize();
         }
 
-        let buffers = MeshBuffers::new("Tetrahedron", device, &vertices, &indices, &edge_indices);
+        let buffers = MeshBuffers::new(
+            MeshType::Tetrahedron,
+            device,
+            &vertices,
+            &indices,
+            &edge_indices,
+        );
 
         Mesh {
             vertices: vertices.to_vec(),
             indices: indices.to_vec(),
             edge_indices: edge_indices.to_vec(),
+            bounding_radius: bounding_radius(&vertices),
+            bounds: vertex_bounds(&vertices),
             buffers,
         }
     }
 
+    /// UV sphere with `divisions` latitude bands, at least 2
     // TODO (mmckenna) : Review this and use Rust idioms
     pub fn new_sphere(device: &wgpu::Device, divisions: usize) -> Mesh {
         use std::f32::consts::PI;
+        // Fewer than two latitude bands leave no ring between the poles
+        let divisions = divisions.max(2);
         let longitude = 2 * divisions;
         let latitude = divisions;
 
         let n_vertices = 2 + (latitude - 1) * longitude;
-
-        // 2 tris per quad
-        //let n_indices = 6 * longitude * (latitude - 1);
-        //let n_edge_indices = 2 * longitude * ((latitude - 1) + longitude + (latitude - 2));
+        // One triangle per cap segment, two per quad between rings
+        let n_indices = 6 * longitude * (latitude - 1);
 
         let mut idx = 0;
         let mut vertices = vec![Vertex::default(); n_vertices];
@@ -248,6 +554,7 @@ impl Mesh {
         // Top pole
         vertex.position = glam::vec3(0.0, 1.0, 0.0);
         vertex.normal = vertex.position.normalize();
+        vertex.uv = glam::vec2(0.5, 0.0);
         let top_index = idx;
         idx += 1;
 
@@ -265,6 +572,7 @@ impl Mesh {
                 vertex = &mut vertices[idx];
                 vertex.position = glam::vec3(x, y, z);
                 vertex.normal = vertex.position.normalize();
+                vertex.uv = glam::vec2(j as f32 / longitude as f32, i as f32 / latitude as f32);
                 idx += 1;
             }
         }
@@ -273,17 +581,18 @@ impl Mesh {
         vertex = &mut vertices[idx];
         vertex.position = glam::vec3(0.0, -1.0, 0.0);
         vertex.normal = vertex.position.normalize();
+        vertex.uv = glam::vec2(0.5, 1.0);
         let bottom_index = idx;
 
         // === Indices ===
-        let mut indices: Vec<u16> = Vec::new();
+        let mut indices: Vec<u32> = Vec::with_capacity(n_indices);
 
         // Top cap
         for j in 0..longitude {
             let next = (j + 1) % longitude;
-            indices.push(top_index as u16);
-            indices.push(1 + next as u16);
-            indices.push(1 + j as u16);
+            indices.push(top_index as u32);
+            indices.push(1 + next as u32);
+            indices.push(1 + j as u32);
         }
 
         // Middle quads
@@ -299,12 +608,12 @@ impl Mesh {
                 let c = next_row + j;
                 let d = next_row + next;
 
-                indices.push(a as u16);
-                indices.push(b as u16);
-                indices.push(c as u16);
-                indices.push(b as u16);
-                indices.push(d as u16);
-                indices.push(c as u16);
+                indices.push(a as u32);
+                indices.push(b as u32);
+                indices.push(c as u32);
+                indices.push(b as u32);
+                indices.push(d as u32);
+                indices.push(c as u32);
             }
         }
 
@@ -312,49 +621,1201 @@ impl Mesh {
         let base = 1 + (latitude - 2) * longitude;
         for j in 0..longitude {
             let next = (j + 1) % longitude;
-            indices.push((base + j) as u16);
-            indices.push((base + next) as u16);
-            indices.push(bottom_index as u16);
+            indices.push((base + j) as u32);
+            indices.push((base + next) as u32);
+            indices.push(bottom_index as u32);
+        }
+
+        debug_assert_eq!(indices.len(), n_indices);
+
+        let edge_indices = sphere_edge_indices(divisions, 1);
+
+        let buffers =
+            MeshBuffers::new(MeshType::Sphere, device, &vertices, &indices, &edge_indices);
+
+        let bounding_radius = bounding_radius(&vertices);
+        let bounds = vertex_bounds(&vertices);
+        Mesh {
+            vertices,
+            indices,
+            edge_indices,
+            bounding_radius,
+            bounds,
+            buffers,
+        }
+    }
+
+    /// This mesh with an outline of only every `edge_stride`-th latitude
+    /// ring and longitude line, so finely divided spheres keep a readable
+    /// wireframe. Only UV spheres from [`Self::new_sphere`] have rings, other
+    /// meshes are returned unchanged. Replace the renderer's sphere with
+    /// [`crate::renderer::Renderer::add_mesh`] to use it.
+    pub fn with_edge_stride(mut self, device: &wgpu::Device, edge_stride: usize) -> Mesh {
+        if self.mesh_type() != MeshType::Sphere {
+            return self;
+        }
+        // `new_sphere` makes 2d - 1 rings of 2d vertices between the poles
+        let vertex_count = self.vertices.len();
+        let Some(divisions) = (2..vertex_count).find(|d| 2 + (d - 1) * 2 * d >= vertex_count)
+        else {
+            return self;
+        };
+        self.edge_indices = sphere_edge_indices(divisions, edge_stride);
+        self.buffers.edge_index_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&label("Edge Index Buffer", MeshType::Sphere)),
+                contents: bytemuck::cast_slice(&self.edge_indices),
+                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+            });
+        self
+    }
+
+    /// Unit sphere from an icosahedron whose triangles are split in four
+    /// `subdivisions` times, so triangles are near-uniform with no poles.
+    /// The outline draws every triangle edge.
+    pub fn new_icosphere(device: &wgpu::Device, subdivisions: usize) -> Mesh {
+        use std::f32::consts::{PI, TAU};
+        let t = (1.0 + 5_f32.sqrt()) / 2.0;
+        let mut positions: Vec<glam::Vec3> = [
+            (-1.0, t, 0.0),
+            (1.0, t, 0.0),
+            (-1.0, -t, 0.0),
+            (1.0, -t, 0.0),
+            (0.0, -1.0, t),
+            (0.0, 1.0, t),
+            (0.0, -1.0, -t),
+            (0.0, 1.0, -t),
+            (t, 0.0, -1.0),
+            (t, 0.0, 1.0),
+            (-t, 0.0, -1.0),
+            (-t, 0.0, 1.0),
+        ]
+        .into_iter()
+        .map(|(x, y, z)| glam::vec3(x, y, z).normalize())
+        .collect();
+        #[rustfmt::skip]
+        let mut indices: Vec<u32> = vec![
+            0, 11, 5,   0, 5, 1,    0, 1, 7,    0, 7, 10,   0, 10, 11,
+            1, 5, 9,    5, 11, 4,   11, 10, 2,  10, 7, 6,   7, 1, 8,
+            3, 9, 4,    3, 4, 2,    3, 2, 6,    3, 6, 8,    3, 8, 9,
+            4, 9, 5,    2, 4, 11,   6, 2, 10,   8, 6, 7,    9, 8, 1,
+        ];
+
+        for _ in 0..subdivisions {
+            // Edge midpoints shared by the two triangles on either side
+            let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+            let mut midpoint = |a: u32, b: u32| {
+                *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                    let position = (positions[a as usize] + positions[b as usize]).normalize();
+                    positions.push(position);
+                    (positions.len() - 1) as u32
+                })
+            };
+            indices = indices
+                .chunks_exact(3)
+                .flat_map(|triangle| {
+                    let [a, b, c] = [triangle[0], triangle[1], triangle[2]];
+                    let [ab, bc, ca] = [midpoint(a, b), midpoint(b, c), midpoint(c, a)];
+                    [a, ab, ca, b, bc, ab, c, ca, bc, ab, bc, ca]
+                })
+                .collect();
+        }
+
+        let vertices: Vec<Vertex> = positions
+            .iter()
+            .map(|&position| Vertex {
+                position,
+                normal: position,
+                uv: glam::vec2(
+                    0.5 + position.z.atan2(position.x) / TAU,
+                    position.y.clamp(-1.0, 1.0).acos() / PI,
+                ),
+                ..Default::default()
+            })
+            .collect();
+
+        let mut edges = HashSet::new();
+        let mut edge_indices = Vec::new();
+        for triangle in indices.chunks_exact(3) {
+            for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+                let edge = (triangle[a].min(triangle[b]), triangle[a].max(triangle[b]));
+                if edges.insert(edge) {
+                    edge_indices.extend([edge.0, edge.1]);
+                }
+            }
+        }
+
+        let bounds = vertex_bounds(&vertices);
+        let buffers = MeshBuffers::new(
+            MeshType::Icosphere,
+            device,
+            &vertices,
+            &indices,
+            &edge_indices,
+        );
+        Mesh {
+            vertices,
+            indices,
+            edge_indices,
+            bounding_radius: 1.0,
+            bounds,
+            buffers,
+        }
+    }
+
+    /// Unit cylinder along Y: radius 1, from y = -1 to y = 1, with flat caps.
+    /// The outline draws both cap rings and four vertical seams.
+    pub fn new_cylinder(device: &wgpu::Device, segments: usize) -> Mesh {
+        use std::f32::consts::TAU;
+        let segments = segments.max(3);
+        // Side rings repeat the first vertex to close the UV seam
+        let ring = segments + 1;
+
+        let mut vertices = Vec::with_capacity(2 * ring + 2 * (segments + 1));
+        for (v, y) in [(1.0, -1.0), (0.0, 1.0)] {
+            for j in 0..ring {
+                let u = j as f32 / segments as f32;
+                let (z, x) = (u * TAU).sin_cos();
+                let normal = glam::vec3(x, 0.0, z);
+                vertices.push(Vertex {
+                    position: glam::vec3(x, y, z),
+                    normal,
+                    uv: glam::vec2(u, v),
+                    ..Default::default()
+                });
+            }
+        }
+        let mut cap = |y: f32| {
+            let center = vertices.len();
+            let normal = glam::vec3(0.0, y, 0.0);
+            vertices.push(Vertex {
+                position: normal,
+                normal,
+                uv: glam::vec2(0.5, 0.5),
+                ..Default::default()
+            });
+            for j in 0..segments {
+                let (z, x) = (j as f32 / segments as f32 * TAU).sin_cos();
+                vertices.push(Vertex {
+                    position: glam::vec3(x, y, z),
+                    normal,
+                    uv: glam::vec2(0.5 + 0.5 * x, 0.5 + 0.5 * z),
+                    ..Default::default()
+                });
+            }
+            center
+        };
+        let bottom_center = cap(-1.0);
+        let top_center = cap(1.0);
+
+        // === Indices ===
+        let mut indices: Vec<u32> = Vec::with_capacity(12 * segments);
+        for j in 0..segments {
+            let b0 = j;
+            let b1 = j + 1;
+            let t0 = ring + j;
+            let t1 = ring + j + 1;
+            indices.extend([b0, t0, b1, b1, t0, t1].map(|i| i as u32));
+        }
+        for j in 0..segments {
+            let next = (j + 1) % segments;
+            indices.extend(
+                [
+                    bottom_center,
+                    bottom_center + 1 + j,
+                    bottom_center + 1 + next,
+                ]
+                .map(|i| i as u32),
+            );
+            indices
+                .extend([top_center, top_center + 1 + next, top_center + 1 + j].map(|i| i as u32));
         }
 
         // === Edge Indices ===
-        let mut edge_indices: Vec<u16> = Vec::new();
-        for j in 0..longitude {
-            // Top pole to first ring
-            edge_indices.push(top_index as u16);
-            edge_indices.push((1 + j) as u16);
-
-            // Connect rings vertically
-            for i in 0..(latitude - 2) {
-                let current_ring = 1 + i * longitude;
-                let next_ring = current_ring + longitude;
-                edge_indices.push((current_ring + j) as u16);
-                edge_indices.push((next_ring + j) as u16);
+        let mut edge_indices: Vec<u32> = Vec::new();
+        for j in 0..segments {
+            edge_indices.extend([j, j + 1, ring + j, ring + j + 1].map(|i| i as u32));
+        }
+        for j in (0..segments).step_by((segments / 4).max(1)) {
+            edge_indices.extend([j, ring + j].map(|i| i as u32));
+        }
+
+        let buffers = MeshBuffers::new(
+            MeshType::Cylinder,
+            device,
+            &vertices,
+            &indices,
+            &edge_indices,
+        );
+
+        let bounding_radius = bounding_radius(&vertices);
+        let bounds = vertex_bounds(&vertices);
+        Mesh {
+            vertices,
+            indices,
+            edge_indices,
+            bounding_radius,
+            bounds,
+            buffers,
+        }
+    }
+
+    /// Capsule along Y: a cylinder of `radius` from y = -`half_height` to
+    /// `half_height`, closed by hemispheres. Normals are continuous across
+    /// the cap seams. The outline draws both seam rings and four meridians.
+    pub fn new_capsule(
+        device: &wgpu::Device,
+        radius: f32,
+        half_height: f32,
+        segments: usize,
+    ) -> Mesh {
+        use std::f32::consts::{FRAC_PI_2, TAU};
+        let segments = segments.max(3);
+        let cap_rings = (segments / 4).max(2);
+        // Rings repeat the first vertex to close the UV seam
+        let ring = segments + 1;
+        // Pole to seam on the top cap, then seam to pole on the bottom one
+        let rows = 2 * (cap_rings + 1);
+
+        let mut vertices = Vec::with_capacity(rows * ring);
+        for row in 0..rows {
+            let (phi, center) = if row <= cap_rings {
+                (row as f32 / cap_rings as f32 * FRAC_PI_2, half_height)
+            } else {
+                let i = row - cap_rings - 1;
+                (
+                    FRAC_PI_2 + i as f32 / cap_rings as f32 * FRAC_PI_2,
+                    -half_height,
+                )
+            };
+            let (sin_phi, cos_phi) = phi.sin_cos();
+            for j in 0..ring {
+                let u = j as f32 / segments as f32;
+                let (z, x) = (u * TAU).sin_cos();
+                let normal = glam::vec3(sin_phi * x, cos_phi, sin_phi * z);
+                vertices.push(Vertex {
+                    position: normal * radius + glam::Vec3::Y * center,
+                    normal,
+                    uv: glam::vec2(u, row as f32 / (rows - 1) as f32),
+                    ..Default::default()
+                });
             }
+        }
 
-            // Last ring to bottom pole
-            let last_ring = 1 + (latitude - 2) * longitude;
-            edge_indices.push((last_ring + j) as u16);
-            edge_indices.push((bottom_index) as u16);
+        // === Indices ===
+        let mut indices: Vec<u32> = Vec::new();
+        for row in 0..rows - 1 {
+            for j in 0..segments {
+                let a0 = row * ring + j;
+                let a1 = a0 + 1;
+                let b0 = a0 + ring;
+                let b1 = b0 + 1;
+                // Skip the zero-area half of each quad touching a pole
+                if row != 0 {
+                    indices.extend([a0, a1, b0].map(|i| i as u32));
+                }
+                if row != rows - 2 {
+                    indices.extend([a1, b1, b0].map(|i| i as u32));
+                }
+            }
         }
 
-        // Latitude rings (horizontal circles)
-        for i in 1..latitude {
-            let ring_start = 1 + (i - 1) * longitude;
-            for j in 0..longitude {
-                let next = (j + 1) % longitude;
-                edge_indices.push((ring_start + j) as u16);
-                edge_indices.push((ring_start + next) as u16);
+        // === Edge Indices ===
+        let mut edge_indices: Vec<u32> = Vec::new();
+        for seam in [cap_rings, cap_rings + 1] {
+            for j in 0..segments {
+                edge_indices.extend([seam * ring + j, seam * ring + j + 1].map(|i| i as u32));
+            }
+        }
+        for j in (0..segments).step_by((segments / 4).max(1)) {
+            for row in 0..rows - 1 {
+                edge_indices.extend([row * ring + j, (row + 1) * ring + j].map(|i| i as u32));
+            }
+        }
+
+        let buffers = MeshBuffers::new(
+            MeshType::Capsule,
+            device,
+            &vertices,
+            &indices,
+            &edge_indices,
+        );
+
+        let bounding_radius = bounding_radius(&vertices);
+        let bounds = vertex_bounds(&vertices);
+        Mesh {
+            vertices,
+            indices,
+            edge_indices,
+            bounding_radius,
+            bounds,
+            buffers,
+        }
+    }
+
+    /// Arrow along +Y from the origin to y = 1: a shaft of diameter 1 to
+    /// y = 0.8 and a cone head of diameter 2. Scale X and Z for thickness,
+    /// see [`crate::commands::DrawCommandBuilder::arrow_between`].
+    pub fn new_arrow(device: &wgpu::Device) -> Mesh {
+        use std::f32::consts::TAU;
+        const SEGMENTS: usize = 16;
+        const SHAFT_RADIUS: f32 = 0.5;
+        const HEAD_RADIUS: f32 = 1.0;
+        const HEAD_START: f32 = 0.8;
+        let ring = SEGMENTS + 1;
+        let direction = |j: usize| {
+            let (z, x) = (j as f32 / SEGMENTS as f32 * TAU).sin_cos();
+            glam::vec3(x, 0.0, z)
+        };
+
+        let mut vertices = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+        let mut edge_indices: Vec<u32> = Vec::new();
+
+        // Shaft side, rings repeat the first vertex to close the UV seam
+        for (v, y) in [(1.0, 0.0), (0.0, HEAD_START)] {
+            for j in 0..ring {
+                let normal = direction(j);
+                vertices.push(Vertex {
+                    position: normal * SHAFT_RADIUS + glam::Vec3::Y * y,
+                    normal,
+                    uv: glam::vec2(j as f32 / SEGMENTS as f32, v),
+                    ..Default::default()
+                });
             }
         }
+        for j in 0..SEGMENTS {
+            let [b0, b1, t0, t1] = [j, j + 1, ring + j, ring + j + 1].map(|i| i as u32);
+            indices.extend([b0, t0, b1, b1, t0, t1]);
+            edge_indices.extend([b0, b1]);
+        }
+        for j in (0..SEGMENTS).step_by(SEGMENTS / 4) {
+            edge_indices.extend([j, ring + j].map(|i| i as u32));
+        }
 
-        let buffers = MeshBuffers::new("Sphere", device, &vertices, &indices, &edge_indices);
+        // Downward-facing disks closing the shaft and the underside of the
+        // head
+        for (y, radius) in [(0.0, SHAFT_RADIUS), (HEAD_START, HEAD_RADIUS)] {
+            let center = vertices.len() as u32;
+            let normal = -glam::Vec3::Y;
+            vertices.push(Vertex {
+                position: glam::Vec3::Y * y,
+                normal,
+                uv: glam::vec2(0.5, 0.5),
+                ..Default::default()
+            });
+            for j in 0..SEGMENTS {
+                let offset = direction(j);
+                vertices.push(Vertex {
+                    position: offset * radius + glam::Vec3::Y * y,
+                    normal,
+                    uv: glam::vec2(0.5 + 0.5 * offset.x, 0.5 + 0.5 * offset.z),
+                    ..Default::default()
+                });
+            }
+            for j in 0..SEGMENTS {
+                let next = (j + 1) % SEGMENTS;
+                indices.extend([center, center + 1 + j as u32, center + 1 + next as u32]);
+            }
+        }
 
+        // Head side, with one apex vertex per segment so each face's normal
+        // stays tilted by the slope
+        let slope = (1.0 - HEAD_START) / HEAD_RADIUS;
+        let base = vertices.len();
+        for j in 0..ring {
+            let offset = direction(j);
+            vertices.push(Vertex {
+                position: offset * HEAD_RADIUS + glam::Vec3::Y * HEAD_START,
+                normal: (offset + glam::Vec3::Y * slope).normalize(),
+                uv: glam::vec2(j as f32 / SEGMENTS as f32, 1.0),
+                ..Default::default()
+            });
+        }
+        for j in 0..SEGMENTS {
+            let middle = direction(j) + direction(j + 1);
+            vertices.push(Vertex {
+                position: glam::Vec3::Y,
+                normal: (middle.normalize() + glam::Vec3::Y * slope).normalize(),
+                uv: glam::vec2((j as f32 + 0.5) / SEGMENTS as f32, 0.0),
+                ..Default::default()
+            });
+        }
+        for j in 0..SEGMENTS {
+            let apex = base + ring + j;
+            indices.extend([base + j, apex, base + j + 1].map(|i| i as u32));
+            edge_indices.extend([base + j, base + j + 1].map(|i| i as u32));
+        }
+        for j in (0..SEGMENTS).step_by(SEGMENTS / 4) {
+            edge_indices.extend([base + j, base + ring + j].map(|i| i as u32));
+        }
+
+        let buffers = MeshBuffers::new(MeshType::Arrow, device, &vertices, &indices, &edge_indices);
+
+        let bounding_radius = bounding_radius(&vertices);
+        let bounds = vertex_bounds(&vertices);
         Mesh {
             vertices,
             indices,
             edge_indices,
+            bounding_radius,
+            bounds,
             buffers,
         }
     }
+
+    /// Unit cone along Y: base radius 1 at y = -1, apex at y = 1. With
+    /// `smooth_apex` the side faces share one apex vertex and shade smoothly
+    /// around the cone; otherwise each face has its own vertices and normal.
+    pub fn new_cone(device: &wgpu::Device, segments: usize, smooth_apex: bool) -> Mesh {
+        use std::f32::consts::TAU;
+        let segments = segments.max(3);
+        let apex = glam::Vec3::Y;
+        let ring_point = |j: usize| {
+            let (z, x) = (j as f32 / segments as f32 * TAU).sin_cos();
+            glam::vec3(x, -1.0, z)
+        };
+
+        let mut vertices = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+        // Apex vertex each side face (or all of them) ends at
+        let mut apex_indices = Vec::with_capacity(segments);
+        if smooth_apex {
+            // Side normals tilt up by the slope: radius 1 over height 2
+            for j in 0..=segments {
+                let position = ring_point(j);
+                vertices.push(Vertex {
+                    position,
+                    normal: glam::vec3(2.0 * position.x, 1.0, 2.0 * position.z).normalize(),
+                    uv: glam::vec2(j as f32 / segments as f32, 1.0),
+                    ..Default::default()
+                });
+            }
+            // Average of the side face normals around the tip
+            let apex_index = vertices.len();
+            vertices.push(Vertex {
+                position: apex,
+                normal: glam::Vec3::Y,
+                uv: glam::vec2(0.5, 0.0),
+                ..Default::default()
+            });
+            for j in 0..segments {
+                indices.extend([j, apex_index, j + 1].map(|i| i as u32));
+                apex_indices.push(apex_index);
+            }
+        } else {
+            for j in 0..segments {
+                let [b0, b1] = [ring_point(j), ring_point(j + 1)];
+                let normal = (apex - b0).cross(b1 - b0).normalize();
+                let u = (j as f32 + 0.5) / segments as f32;
+                let start = vertices.len();
+                for (position, uv) in [
+                    (b0, glam::vec2(j as f32 / segments as f32, 1.0)),
+                    (apex, glam::vec2(u, 0.0)),
+                    (b1, glam::vec2((j + 1) as f32 / segments as f32, 1.0)),
+                ] {
+                    vertices.push(Vertex {
+                        position,
+                        normal,
+                        uv,
+                        ..Default::default()
+                    });
+                }
+                indices.extend([start, start + 1, start + 2].map(|i| i as u32));
+                apex_indices.push(start + 1);
+            }
+        }
+
+        // Base cap
+        let base_center = vertices.len();
+        vertices.push(Vertex {
+            position: glam::vec3(0.0, -1.0, 0.0),
+            normal: glam::Vec3::NEG_Y,
+            uv: glam::vec2(0.5, 0.5),
+            ..Default::default()
+        });
+        for j in 0..segments {
+            let position = ring_point(j);
+            vertices.push(Vertex {
+                position,
+                normal: glam::Vec3::NEG_Y,
+                uv: glam::vec2(0.5 + 0.5 * position.x, 0.5 + 0.5 * position.z),
+                ..Default::default()
+            });
+        }
+        for j in 0..segments {
+            let next = (j + 1) % segments;
+            indices.extend(
+                [base_center, base_center + 1 + j, base_center + 1 + next].map(|i| i as u32),
+            );
+        }
+
+        // === Edge Indices ===
+        let mut edge_indices: Vec<u32> = Vec::new();
+        for j in 0..segments {
+            let next = (j + 1) % segments;
+            edge_indices.extend([base_center + 1 + j, base_center + 1 + next].map(|i| i as u32));
+        }
+        for j in (0..segments).step_by((segments / 4).max(1)) {
+            edge_indices.extend([base_center + 1 + j, apex_indices[j]].map(|i| i as u32));
+        }
+
+        let buffers = MeshBuffers::new(MeshType::Cone, device, &vertices, &indices, &edge_indices);
+
+        let bounding_radius = bounding_radius(&vertices);
+        let bounds = vertex_bounds(&vertices);
+        Mesh {
+            vertices,
+            indices,
+            edge_indices,
+            bounding_radius,
+            bounds,
+            buffers,
+        }
+    }
+
+    /// Torus around Y with major radius 1 and a tube of `minor_radius`.
+    /// Normals point away from the tube's center line. The outline draws
+    /// every major and minor ring.
+    pub fn new_torus(
+        device: &wgpu::Device,
+        major_segments: usize,
+        minor_segments: usize,
+        minor_radius: f32,
+    ) -> Mesh {
+        use std::f32::consts::TAU;
+        let major_segments = major_segments.max(3);
+        let minor_segments = minor_segments.max(3);
+        // Both rings repeat their first vertex to close the UV seams
+        let minor_ring = minor_segments + 1;
+
+        let mut vertices = Vec::with_capacity((major_segments + 1) * minor_ring);
+        for i in 0..=major_segments {
+            let u = i as f32 / major_segments as f32;
+            let (z, x) = (u * TAU).sin_cos();
+            let radial = glam::vec3(x, 0.0, z);
+            for j in 0..=minor_segments {
+                let v = j as f32 / minor_segments as f32;
+                let (sin_v, cos_v) = (v * TAU).sin_cos();
+                let normal = radial * cos_v + glam::Vec3::Y * sin_v;
+                vertices.push(Vertex {
+                    position: radial + normal * minor_radius,
+                    normal,
+                    uv: glam::vec2(u, v),
+                    ..Default::default()
+                });
+            }
+        }
+
+        // === Indices ===
+        let mut indices: Vec<u32> = Vec::with_capacity(6 * major_segments * minor_segments);
+        for i in 0..major_segments {
+            for j in 0..minor_segments {
+                let a = i * minor_ring + j;
+                let b = a + minor_ring;
+                let c = a + 1;
+                let d = b + 1;
+                indices.extend([a, c, b, b, c, d].map(|i| i as u32));
+            }
+        }
+
+        // === Edge Indices ===
+        let mut edge_indices: Vec<u32> = Vec::new();
+        for i in 0..major_segments {
+            for j in 0..minor_segments {
+                let a = i * minor_ring + j;
+                // Along the minor ring, then along the major ring
+                edge_indices.extend([a, a + 1, a, a + minor_ring].map(|i| i as u32));
+            }
+        }
+
+        let buffers = MeshBuffers::new(MeshType::Torus, device, &vertices, &indices, &edge_indices);
+
+        let bounding_radius = bounding_radius(&vertices);
+        let bounds = vertex_bounds(&vertices);
+        Mesh {
+            vertices,
+            indices,
+            edge_indices,
+            bounding_radius,
+            bounds,
+            buffers,
+        }
+    }
+
+    /// Unit quad in the XY plane facing +Z, from -0.5 to 0.5. Drawn as
+    /// `MeshType::Billboard` it is turned to face the camera, sized by the
+    /// instance's X and Y scale.
+    pub fn new_billboard(device: &wgpu::Device) -> Mesh {
+        let vertices: Vec<Vertex> = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]
+            .into_iter()
+            .map(|(u, v)| Vertex {
+                position: glam::vec3(u - 0.5, v - 0.5, 0.0),
+                normal: glam::Vec3::Z,
+                uv: glam::vec2(u, 1.0 - v),
+                ..Default::default()
+            })
+            .collect();
+        let indices = [0, 1, 2, 0, 2, 3];
+        let edge_indices = [0, 1, 1, 2, 2, 3, 3, 0];
+
+        let buffers = MeshBuffers::new(
+            MeshType::Billboard,
+            device,
+            &vertices,
+            &indices,
+            &edge_indices,
+        );
+
+        let bounding_radius = bounding_radius(&vertices);
+        let bounds = vertex_bounds(&vertices);
+        Mesh {
+            vertices,
+            indices: indices.to_vec(),
+            edge_indices: edge_indices.to_vec(),
+            bounding_radius,
+            bounds,
+            buffers,
+        }
+    }
+
+    /// Square in the XZ plane spanning [-1, 1], facing +Y, split into
+    /// `subdivisions` cells along each axis. The outline is the border, plus
+    /// every cell edge when `grid_edges` is set.
+    pub fn new_plane(device: &wgpu::Device, subdivisions: usize, grid_edges: bool) -> Mesh {
+        let n = subdivisions.max(1);
+        let row = n + 1;
+
+        let mut vertices = Vec::with_capacity(row * row);
+        for k in 0..row {
+            for i in 0..row {
+                let uv = glam::vec2(i as f32, k as f32) / n as f32;
+                vertices.push(Vertex {
+                    position: glam::vec3(2.0 * uv.x - 1.0, 0.0, 2.0 * uv.y - 1.0),
+                    normal: glam::Vec3::Y,
+                    uv,
+                    ..Default::default()
+                });
+            }
+        }
+
+        // === Indices ===
+        // Counter-clockwise seen from above, so back-face culling keeps the
+        // top side
+        let mut indices: Vec<u32> = Vec::with_capacity(6 * n * n);
+        for k in 0..n {
+            for i in 0..n {
+                let a = k * row + i;
+                let b = a + 1;
+                let c = a + row;
+                let d = c + 1;
+                indices.extend([a, c, b, b, c, d].map(|i| i as u32));
+            }
+        }
+
+        // === Edge Indices ===
+        let lines: Vec<usize> = if grid_edges {
+            (0..row).collect()
+        } else {
+            vec![0, n]
+        };
+        let mut edge_indices: Vec<u32> = Vec::new();
+        for &line in &lines {
+            for step in 0..n {
+                // Along X at z = line, then along Z at x = line
+                let x = line * row + step;
+                let z = step * row + line;
+                edge_indices.extend([x, x + 1, z, z + row].map(|i| i as u32));
+            }
+        }
+
+        let buffers = MeshBuffers::new(MeshType::Plane, device, &vertices, &indices, &edge_indices);
+
+        let bounding_radius = bounding_radius(&vertices);
+        let bounds = vertex_bounds(&vertices);
+        Mesh {
+            vertices,
+            indices,
+            edge_indices,
+            bounding_radius,
+            bounds,
+            buffers,
+        }
+    }
+
+    /// Tube of `radius` swept along the polyline `points`, with flat end
+    /// caps. Registered under a new [`MeshType::Custom`] id; add it to a
+    /// renderer with [`crate::renderer::Renderer::add_mesh`]. Repeated
+    /// points are skipped, and `None` is returned when fewer than two
+    /// distinct points remain.
+    pub fn new_tube_along_path(
+        device: &wgpu::Device,
+        points: &[glam::Vec3],
+        radius: f32,
+        radial_segments: usize,
+    ) -> Option<Mesh> {
+        let (vertices, indices, edge_indices) = tube_geometry(points, radius, radial_segments)?;
+        Some(Self::from_data(device, &vertices, &indices, &edge_indices))
+    }
+
+    /// Line list from each vertex of `source` to `length` along its
+    /// normal, for checking generated normals. Drawn with `draw_edges`.
+    pub fn new_normal_lines(device: &wgpu::Device, source: &Mesh, length: f32) -> Mesh {
+        let vertices: Vec<Vertex> = source
+            .vertices
+            .iter()
+            .flat_map(|vertex| {
+                [
+                    *vertex,
+                    Vertex {
+                        position: vertex.position + vertex.normal * length,
+                        ..*vertex
+                    },
+                ]
+            })
+            .collect();
+        let edge_indices: Vec<u32> = (0..vertices.len() as u32).collect();
+        Mesh::from_data_as(source.mesh_type(), device, &vertices, &[], &edge_indices)
+    }
+
+    /// Mesh from caller-generated geometry: triangle list `indices` and line
+    /// list `edge_indices` into `vertices`. Registered under a new
+    /// [`MeshType::Custom`] id; add it to a renderer with
+    /// [`crate::renderer::Renderer::add_mesh`].
+    pub fn from_data(
+        device: &wgpu::Device,
+        vertices: &[Vertex],
+        indices: &[u32],
+        edge_indices: &[u32],
+    ) -> Mesh {
+        Self::from_data_as(
+            MeshType::next_custom(),
+            device,
+            vertices,
+            indices,
+            edge_indices,
+        )
+    }
+
+    /// Like [`Mesh::from_data`], but labelled `mesh_type` without taking a
+    /// new `Custom` id, for helper meshes the renderer keeps to itself
+    pub(crate) fn from_data_as(
+        mesh_type: MeshType,
+        device: &wgpu::Device,
+        vertices: &[Vertex],
+        indices: &[u32],
+        edge_indices: &[u32],
+    ) -> Mesh {
+        let buffers = MeshBuffers::new(mesh_type, device, vertices, indices, edge_indices);
+
+        Mesh {
+            vertices: vertices.to_vec(),
+            indices: indices.to_vec(),
+            edge_indices: edge_indices.to_vec(),
+            bounding_radius: bounding_radius(vertices),
+            bounds: vertex_bounds(vertices),
+            buffers,
+        }
+    }
+
+    /// Mesh from an OBJ file, see [`crate::import::load_obj`]. Registered
+    /// under a new [`MeshType::Custom`] id like [`Mesh::from_data`].
+    /// Coordinates are kept as-is, see [`Mesh::from_obj_with`].
+    #[cfg(feature = "obj")]
+    pub fn from_obj(
+        device: &wgpu::Device,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<Mesh, RaidenError> {
+        Self::from_obj_with(device, path, import::ImportTransform::default())
+    }
+
+    /// Like [`Mesh::from_obj`], with `transform` applied to the geometry,
+    /// e.g. `UpAxis::Y` for most OBJ exports
+    #[cfg(feature = "obj")]
+    pub fn from_obj_with(
+        device: &wgpu::Device,
+        path: impl AsRef<std::path::Path>,
+        transform: import::ImportTransform,
+    ) -> Result<Mesh, RaidenError> {
+        let mut data = import::load_obj(path)?;
+        transform.apply(&mut data.vertices, &mut data.indices);
+        Ok(Self::from_data(
+            device,
+            &data.vertices,
+            &data.indices,
+            &data.edge_indices,
+        ))
+    }
+
+    /// Mesh from a glTF file's first primitive, and a command per scene node
+    /// that places it, see [`crate::import::load_gltf`]. Registered under a
+    /// new [`MeshType::Custom`] id like [`Mesh::from_data`]. Coordinates are
+    /// kept Y-up, see [`Mesh::from_gltf_with`].
+    #[cfg(feature = "gltf")]
+    pub fn from_gltf(
+        device: &wgpu::Device,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<(Mesh, Vec<DrawCommand>), RaidenError> {
+        Self::from_gltf_with(device, path, import::ImportTransform::default())
+    }
+
+    /// Like [`Mesh::from_gltf`], with `transform` applied to the geometry and
+    /// each node's placement, e.g. `UpAxis::Y` to stand glTF scenes up
+    #[cfg(feature = "gltf")]
+    pub fn from_gltf_with(
+        device: &wgpu::Device,
+        path: impl AsRef<std::path::Path>,
+        transform: import::ImportTransform,
+    ) -> Result<(Mesh, Vec<DrawCommand>), RaidenError> {
+        let (mut data, instances) = import::load_gltf(path)?;
+        transform.apply(&mut data.vertices, &mut data.indices);
+        let mesh = Self::from_data(device, &data.vertices, &data.indices, &data.edge_indices);
+        let commands = instances
+            .into_iter()
+            .map(|instance| {
+                let instance = Instance {
+                    model_matrix: transform.apply_to_model(instance.model_matrix),
+                    ..instance
+                };
+                DrawCommand::from_instance(mesh.mesh_type(), instance)
+            })
+            .collect();
+        Ok((mesh, commands))
+    }
+}
+
+/// Radius of the origin-centered sphere enclosing `vertices`
+pub fn bounding_radius(vertices: &[Vertex]) -> f32 {
+    vertices
+        .iter()
+        .map(|vertex| vertex.position.length())
+        .fold(0.0, f32::max)
+}
+
+/// Axis-aligned `(min, max)` of `vertices`' positions, or zero for none
+pub fn vertex_bounds(vertices: &[Vertex]) -> (glam::Vec3, glam::Vec3) {
+    if vertices.is_empty() {
+        return (glam::Vec3::ZERO, glam::Vec3::ZERO);
+    }
+    vertices.iter().fold(
+        (glam::Vec3::splat(f32::MAX), glam::Vec3::splat(f32::MIN)),
+        |(min, max), vertex| (min.min(vertex.position), max.max(vertex.position)),
+    )
+}
+
+pub(crate) fn raycast_triangles(
+    vertices: &[Vertex],
+    indices: &[u32],
+    ray_origin: glam::Vec3,
+    ray_dir: glam::Vec3,
+    model: glam::Mat4,
+) -> Option<f32> {
+    // An affine transform keeps `t` the same, so test in model space
+    let inverse = model.inverse();
+    let origin = inverse.transform_point3(ray_origin);
+    let dir = inverse.transform_vector3(ray_dir);
+
+    let (min, max) = vertex_bounds(vertices);
+    ray_hits_aabb(origin, dir, min, max)?;
+
+    indices
+        .chunks_exact(3)
+        .filter_map(|tri| {
+            let [a, b, c] = [0, 1, 2].map(|k| vertices[tri[k] as usize].position);
+            ray_triangle(origin, dir, a, b, c)
+        })
+        .min_by(f32::total_cmp)
+}
+
+/// Entry `t` of a ray through a box (slab test), `None` on a miss
+pub(crate) fn ray_hits_aabb(
+    origin: glam::Vec3,
+    dir: glam::Vec3,
+    min: glam::Vec3,
+    max: glam::Vec3,
+) -> Option<f32> {
+    let inv_dir = dir.recip();
+    let t0 = (min - origin) * inv_dir;
+    let t1 = (max - origin) * inv_dir;
+    let t_near = t0.min(t1).max_element();
+    let t_far = t0.max(t1).min_element();
+    (t_near <= t_far && t_far >= 0.0).then_some(t_near)
+}
+
+/// Möller–Trumbore ray/triangle intersection
+fn ray_triangle(
+    origin: glam::Vec3,
+    dir: glam::Vec3,
+    a: glam::Vec3,
+    b: glam::Vec3,
+    c: glam::Vec3,
+) -> Option<f32> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = dir.cross(edge2);
+    let det = edge1.dot(p);
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+    let inv_det = det.recip();
+    let s = origin - a;
+    let u = s.dot(p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(edge1);
+    let v = dir.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = edge2.dot(q) * inv_det;
+    (t >= 0.0).then_some(t)
+}
+
+/// Most grid lines `grid_geometry` puts on each side of a center line
+pub const MAX_GRID_HALF_LINES: u32 = 1000;
+
+/// Vertices of a reference grid on the XZ plane with lines every `spacing`
+/// out to `half_extent`, and line list indices for the grid lines and for
+/// the two center lines along the X and Z axes. Spacing is widened to keep
+/// within [`MAX_GRID_HALF_LINES`]; a `half_extent` that isn't positive and
+/// finite gives no geometry.
+pub(crate) fn grid_geometry(half_extent: f32, spacing: f32) -> (Vec<Vertex>, Vec<u32>, Vec<u32>) {
+    if !half_extent.is_finite() || half_extent <= 0.0 || spacing.is_nan() {
+        return (Vec::new(), Vec::new(), Vec::new());
+    }
+    let spacing = spacing.max(half_extent / MAX_GRID_HALF_LINES as f32);
+    let n = ((half_extent / spacing).floor() as i32).min(MAX_GRID_HALF_LINES as i32);
+    let mut vertices = Vec::with_capacity(8 * n as usize + 4);
+    let mut line_indices = Vec::new();
+    let mut axis_indices = Vec::new();
+    for i in -n..=n {
+        let offset = i as f32 * spacing;
+        let start = vertices.len() as u32;
+        for (a, b) in [(-half_extent, offset), (half_extent, offset)] {
+            vertices.push(Vertex {
+                position: glam::vec3(a, 0.0, b),
+                color: glam::Vec3::ONE,
+                normal: glam::Vec3::Y,
+                ..Default::default()
+            });
+        }
+        for (a, b) in [(offset, -half_extent), (offset, half_extent)] {
+            vertices.push(Vertex {
+                position: glam::vec3(a, 0.0, b),
+                color: glam::Vec3::ONE,
+                normal: glam::Vec3::Y,
+                ..Default::default()
+            });
+        }
+        let indices = if i == 0 {
+            &mut axis_indices
+        } else {
+            &mut line_indices
+        };
+        indices.extend([start, start + 1, start + 2, start + 3]);
+    }
+    (vertices, line_indices, axis_indices)
+}
+
+/// Line list for a [`Mesh::new_sphere`] of `divisions`: every
+/// `edge_stride`-th meridian from pole to pole and every `edge_stride`-th
+/// latitude ring
+fn sphere_edge_indices(divisions: usize, edge_stride: usize) -> Vec<u32> {
+    let edge_stride = edge_stride.max(1);
+    let longitude = 2 * divisions;
+    let latitude = divisions;
+    let top_index = 0;
+    let bottom_index = 1 + (latitude - 1) * longitude;
+    // Meridians from pole to pole plus the latitude rings, when every line
+    // is drawn
+    let n_edge_indices = 2 * longitude * latitude + 2 * longitude * (latitude - 1);
+
+    let mut edge_indices: Vec<u32> = Vec::with_capacity(n_edge_indices);
+    for j in (0..longitude).step_by(edge_stride) {
+        // Top pole to first ring
+        edge_indices.push(top_index as u32);
+        edge_indices.push((1 + j) as u32);
+
+        // Connect rings vertically
+        for i in 0..(latitude - 2) {
+            let current_ring = 1 + i * longitude;
+            let next_ring = current_ring + longitude;
+            edge_indices.push((current_ring + j) as u32);
+            edge_indices.push((next_ring + j) as u32);
+        }
+
+        // Last ring to bottom pole
+        let last_ring = 1 + (latitude - 2) * longitude;
+        edge_indices.push((last_ring + j) as u32);
+        edge_indices.push(bottom_index as u32);
+    }
+
+    // Latitude rings (horizontal circles)
+    for i in (1..latitude).filter(|i| i % edge_stride == 0) {
+        let ring_start = 1 + (i - 1) * longitude;
+        for j in 0..longitude {
+            let next = (j + 1) % longitude;
+            edge_indices.push((ring_start + j) as u32);
+            edge_indices.push((ring_start + next) as u32);
+        }
+    }
+    debug_assert!(edge_stride > 1 || edge_indices.len() == n_edge_indices);
+    edge_indices
+}
+
+/// Vertices, triangle indices and edge indices for
+/// [`Mesh::new_tube_along_path`]. Rings are oriented with parallel-transport
+/// frames: each ring's normal is the previous one rotated by the minimal
+/// rotation between tangents, so the tube doesn't twist around the path.
+/// `None` when the path has fewer than two distinct points.
+pub(crate) fn tube_geometry(
+    points: &[glam::Vec3],
+    radius: f32,
+    radial_segments: usize,
+) -> Option<(Vec<Vertex>, Vec<u32>, Vec<u32>)> {
+    use std::f32::consts::TAU;
+
+    // Coincident points have no tangent
+    let mut path: Vec<glam::Vec3> = Vec::with_capacity(points.len());
+    for &point in points {
+        if path
+            .last()
+            .is_none_or(|last| last.distance_squared(point) > f32::EPSILON)
+        {
+            path.push(point);
+        }
+    }
+    if path.len() < 2 {
+        return None;
+    }
+    let segments = radial_segments.max(3);
+    let rings = path.len();
+
+    let tangents: Vec<glam::Vec3> = (0..rings)
+        .map(|i| {
+            let prev = path[i.saturating_sub(1)];
+            let next = path[(i + 1).min(rings - 1)];
+            // Where the path doubles back, follow the incoming segment
+            (next - prev)
+                .try_normalize()
+                .unwrap_or_else(|| (path[i] - prev).normalize())
+        })
+        .collect();
+
+    let mut frames = Vec::with_capacity(rings);
+    let mut normal = tangents[0].any_orthonormal_vector();
+    for i in 0..rings {
+        if i > 0 {
+            let transport = glam::Quat::from_rotation_arc(tangents[i - 1], tangents[i]);
+            normal = transport * normal;
+            // Remove drift so the frame stays orthonormal
+            normal = (normal - tangents[i] * normal.dot(tangents[i])).normalize();
+        }
+        frames.push((normal, tangents[i].cross(normal)));
+    }
+
+    let n_vertices = rings * segments + 2 * (segments + 1);
+
+    let mut vertices = Vec::with_capacity(n_vertices);
+    for (i, (&center, &(normal, binormal))) in path.iter().zip(&frames).enumerate() {
+        for j in 0..segments {
+            let theta = j as f32 * TAU / segments as f32;
+            let radial = normal * theta.cos() + binormal * theta.sin();
+            vertices.push(
+                Vertex::new(center + radial * radius, glam::Vec3::ONE, radial).with_uv(glam::vec2(
+                    j as f32 / segments as f32,
+                    i as f32 / (rings - 1) as f32,
+                )),
+            );
+        }
+    }
+
+    let mut indices: Vec<u32> = Vec::new();
+    for i in 0..rings - 1 {
+        let row = i * segments;
+        let next_row = row + segments;
+        for j in 0..segments {
+            let next = (j + 1) % segments;
+            let a = (row + j) as u32;
+            let b = (row + next) as u32;
+            let c = (next_row + j) as u32;
+            let d = (next_row + next) as u32;
+            indices.extend_from_slice(&[a, b, c, b, d, c]);
+        }
+    }
+
+    // Caps get their own vertices so they shade flat
+    for (ring, facing) in [(0, -1.0), (rings - 1, 1.0)] {
+        let normal = tangents[ring] * facing;
+        let center = vertices.len() as u32;
+        vertices.push(Vertex::new(path[ring], glam::Vec3::ONE, normal));
+        for j in 0..segments {
+            let side = vertices[ring * segments + j];
+            vertices.push(Vertex::new(side.position, glam::Vec3::ONE, normal));
+        }
+        for j in 0..segments {
+            let next = (j + 1) % segments;
+            let a = center + 1 + j as u32;
+            let b = center + 1 + next as u32;
+            if facing > 0.0 {
+                indices.extend_from_slice(&[center, a, b]);
+            } else {
+                indices.extend_from_slice(&[center, b, a]);
+            }
+        }
+    }
+
+    // End rings plus four seams along the path
+    let mut edge_indices: Vec<u32> = Vec::new();
+    for ring in [0, rings - 1] {
+        for j in 0..segments {
+            let next = (j + 1) % segments;
+            edge_indices.push((ring * segments + j) as u32);
+            edge_indices.push((ring * segments + next) as u32);
+        }
+    }
+    for quarter in 0..4 {
+        let j = quarter * segments / 4;
+        for i in 0..rings - 1 {
+            edge_indices.push((i * segments + j) as u32);
+            edge_indices.push(((i + 1) * segments + j) as u32);
+        }
+    }
+
+    Some((vertices, indices, edge_indices))
+}
+
+/// Draw `instances` in chunks of at most `capacity`, the size of the
+/// persistent `instance_buffer`. Only scenes larger than the device's buffer
+/// size limit (256 MiB on WebGL2) spill into temporary buffers; everything
+/// else is a single draw call.
+fn draw_chunked(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    render_pass: &mut wgpu::RenderPass<'_>,
+    instance_buffer: &wgpu::Buffer,
+    capacity: usize,
+    index_count: u32,
+    instances: &[Instance],
+) {
+    let mut chunks = instances.chunks(capacity.max(1));
+    let Some(first) = chunks.next() else {
+        return;
+    };
+    queue.write_buffer(instance_buffer, 0, bytemuck::cast_slice(first));
+    render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+    render_pass.draw_indexed(0..index_count, 0, 0..first.len() as u32);
+
+    // Each chunk needs its own buffer: writes are applied before the pass
+    // runs, so reusing one buffer would draw the last chunk repeatedly.
+    for chunk in chunks {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Overflow Instance Buffer"),
+            contents: bytemuck::cast_slice(chunk),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        render_pass.set_vertex_buffer(1, buffer.slice(..));
+        render_pass.draw_indexed(0..index_count, 0, 0..chunk.len() as u32);
+    }
 }
@@ -43,7 +43,7 @@ pub const CUBE_VERTICES: &[Vertex] = &[
 ];
 
 #[rustfmt::skip]
-pub const CUBE_INDICES : &[u16] = &[
+pub const CUBE_INDICES : &[u32] = &[
     // Front
     0, 1, 3,
     0, 3, 2,
@@ -65,7 +65,7 @@ pub const CUBE_INDICES : &[u16] = &[
 ];
 
 #[rustfmt::skip]
-pub const CUBE_EDGES : &[u16] = &[
+pub const CUBE_EDGES : &[u32] = &[
     0, 1,
     1, 3,
     3, 2,
@@ -81,3 +81,34 @@ pub const CUBE_EDGES : &[u16] = &[
     2, 6,
     3, 7,
 ];
+
+/// Single triangle in the XY plane facing +Z
+pub const TRIANGLE_VERTICES: &[Vertex] = &[
+    Vertex::new(
+        glam::Vec3::new(0.0, 1.0, 0.0),
+        glam::Vec3::new(1.0, 1.0, 1.0),
+        glam::Vec3::new(0.0, 0.0, 1.0),
+    ),
+    Vertex::new(
+        glam::Vec3::new(-0.866, -0.5, 0.0),
+        glam::Vec3::new(1.0, 1.0, 1.0),
+        glam::Vec3::new(0.0, 0.0, 1.0),
+    ),
+    Vertex::new(
+        glam::Vec3::new(0.866, -0.5, 0.0),
+        glam::Vec3::new(1.0, 1.0, 1.0),
+        glam::Vec3::new(0.0, 0.0, 1.0),
+    ),
+];
+
+#[rustfmt::skip]
+pub const TRIANGLE_INDICES : &[u32] = &[
+    0, 1, 2,
+];
+
+#[rustfmt::skip]
+pub const TRIANGLE_EDGES : &[u32] = &[
+    0, 1,
+    1, 2,
+    2, 0,
+];
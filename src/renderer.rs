@@ -1,31 +1,228 @@
-use super::camera::Camera;
-use super::commands::DrawCommand;
-use super::mesh::{Mesh, MeshType, Vertex};
+use super::camera::{Camera, Frustum, PanOrbitCamera};
+use super::commands::{
+    self, BatchHandle, CommandHandle, CommandId, DrawCommand, InstanceBatch, OutlineStyle,
+    RetainedCommands,
+};
+use super::error::RaidenError;
+use super::mesh::{self, Mesh, MeshType, Vertex};
 use super::shaders;
-use std::collections::HashMap;
-use std::sync::Arc;
+use super::timing::{FrameTimings, GpuTimer};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Range;
 use wgpu::util::DeviceExt;
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Uniforms {
     view_proj: glam::Mat4,
+    inv_view_proj: glam::Mat4,
+    fog_color: glam::Vec4,
+    light_direction: glam::Vec4,
+    /// Light color in rgb, intensity in w
+    light_color: glam::Vec4,
+    shade_mode: u32,
+    fog_mode: u32,
+    fog_start: f32,
+    fog_end: f32,
+    fog_density: f32,
+    clip_plane_count: u32,
+    blend_mode: u32,
+    point_light_count: u32,
+    color_mode: u32,
+    outline_scale: f32,
+    _padding: [u32; 2],
+    outline_color: glam::Vec4,
+    clip_planes: [glam::Vec4; MAX_CLIP_PLANES],
+}
+
+/// Color of the lines drawn by `Renderer::set_normal_debug`
+pub const NORMAL_DEBUG_COLOR: glam::Vec4 = glam::Vec4::new(1.0, 1.0, 0.0, 1.0);
+
+/// Number of clip planes the shader supports, see `Renderer::set_clip_planes`
+pub const MAX_CLIP_PLANES: usize = 4;
+
+impl Uniforms {
+    pub fn new(view_proj: glam::Mat4) -> Self {
+        Self {
+            view_proj,
+            inv_view_proj: view_proj.inverse(),
+            fog_color: glam::Vec4::ZERO,
+            light_direction: DirectionalLight::default().direction.extend(0.0),
+            light_color: DirectionalLight::default().color_intensity(),
+            shade_mode: ShadeMode::default() as u32,
+            fog_mode: 0,
+            fog_start: 0.0,
+            fog_end: 0.0,
+            fog_density: 0.0,
+            clip_plane_count: 0,
+            blend_mode: BlendMode::default() as u32,
+            point_light_count: 0,
+            color_mode: ColorMode::default() as u32,
+            outline_scale: OutlineStyle::default().scale,
+            _padding: [0; 2],
+            outline_color: OutlineStyle::default().color,
+            clip_planes: [glam::Vec4::ZERO; MAX_CLIP_PLANES],
+        }
+    }
+}
+
+#[repr(u32)]
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum ShadeMode {
+    /// Interpolated vertex normals
+    #[default]
+    Smooth = 0,
+    /// Per-face normals derived from screen-space derivatives of the world
+    /// position. Facets any mesh without duplicating vertices.
+    FlatDerivative = 1,
+}
+
+/// Where the color of solid surfaces comes from. Outlines, edges and
+/// picking always use the instance color.
+#[repr(u32)]
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum ColorMode {
+    /// `Instance::color`, ignoring vertex colors
+    #[default]
+    Instance = 0,
+    /// `Vertex::color`, e.g. baked into an imported mesh
+    Vertex = 1,
+    /// Vertex color tinted by the instance color
+    Product = 2,
+}
+
+/// How translucent solids are composited over what is behind them
+#[repr(u32)]
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum BlendMode {
+    /// Shader outputs unassociated color, blended by its alpha
+    #[default]
+    Straight = 0,
+    /// Shader outputs color already multiplied by alpha. Composites layered
+    /// translucency correctly and suits surfaces overlaid on other content.
+    Premultiplied = 1,
+}
+
+impl BlendMode {
+    fn blend_state(self) -> wgpu::BlendState {
+        match self {
+            BlendMode::Straight => alpha_blend_state(),
+            BlendMode::Premultiplied => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+        }
+    }
+}
+
+#[repr(u32)]
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum FogMode {
+    /// Ramps from no fog at `start` to full fog at `end`
+    #[default]
+    Linear = 1,
+    /// `1 - exp(-density * (depth - start))`, ignoring `end`
+    Exp = 2,
+}
+
+/// Distance fog blending geometry toward `color` by view-space depth. The
+/// color should usually match the clear color so distant objects fade into
+/// the background.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Fog {
+    pub mode: FogMode,
+    pub color: glam::Vec3,
+    pub start: f32,
+    pub end: f32,
+    pub density: f32,
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Self {
+            mode: FogMode::Linear,
+            color: glam::Vec3::splat(0.01),
+            start: 10.0,
+            end: 100.0,
+            density: 0.05,
+        }
+    }
+}
+
+/// Sun-like light shining along `direction` from infinitely far away, added
+/// on top of a constant ambient term
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DirectionalLight {
+    /// Direction the light travels, need not be normalized
+    pub direction: glam::Vec3,
+    pub color: glam::Vec3,
+    pub intensity: f32,
+}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        Self {
+            direction: glam::vec3(-0.3, 0.5, -0.8).normalize(),
+            color: glam::Vec3::ONE,
+            intensity: 0.5,
+        }
+    }
+}
+
+impl DirectionalLight {
+    fn color_intensity(&self) -> glam::Vec4 {
+        self.color.extend(self.intensity)
+    }
+}
+
+/// Light radiating from `position`, fading out completely at `range`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PointLight {
+    pub position: glam::Vec3,
+    pub color: glam::Vec3,
+    pub intensity: f32,
+    pub range: f32,
+}
+
+/// Point lights the shader can read without storage buffers, e.g. on WebGL2
+pub const MAX_UNIFORM_POINT_LIGHTS: usize = 16;
+
+/// `PointLight` as laid out in the shader
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct PointLightUniform {
+    position_range: glam::Vec4,
+    color_intensity: glam::Vec4,
+}
+
+impl From<&PointLight> for PointLightUniform {
+    fn from(light: &PointLight) -> Self {
+        Self {
+            position_range: light.position.extend(light.range),
+            color_intensity: light.color.extend(light.intensity),
+        }
+    }
 }
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Instance {
     pub model_matrix: glam::Mat4,
     pub color: glam::Vec4,
+    /// Texture atlas region as `(offset.x, offset.y, scale.x, scale.y)`,
+    /// mapping mesh UVs to `offset + uv * scale` before sampling
+    pub uv_region: glam::Vec4,
 }
 
 impl Instance {
-    const ATTRIBS: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
-        3 => Float32x4,
+    /// `uv_region` that samples the whole texture
+    pub const FULL_UV_REGION: glam::Vec4 = glam::Vec4::new(0.0, 0.0, 1.0, 1.0);
+
+    const ATTRIBS: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
         4 => Float32x4,
         5 => Float32x4,
         6 => Float32x4,
-        7 => Float32x4
+        7 => Float32x4,
+        8 => Float32x4,
+        9 => Float32x4
     ];
     pub fn desc() -> wgpu::VertexBufferLayout<'static> {
         use std::mem;
@@ -38,6 +235,12 @@ impl Instance {
     }
 }
 
+// Vertex and instance attributes together must fit WebGL2's attribute limit
+const _: () = assert!(
+    mesh::Vertex::ATTRIBS.len() + Instance::ATTRIBS.len()
+        <= wgpu::Limits::downlevel_webgl2_defaults().max_vertex_attributes as usize
+);
+
 impl Instance {
     pub fn set_position(&mut self, position: glam::Vec3) {
         self.model_matrix.w_axis.x = position.x;
@@ -45,60 +248,343 @@ impl Instance {
         self.model_matrix.w_axis.z = position.z;
     }
 
+    /// Replace the rotation, keeping position and scale
+    pub fn set_rotation(&mut self, rotation: glam::Mat3) {
+        let (scale, _, translation) = self.model_matrix.to_scale_rotation_translation();
+        self.model_matrix = glam::Mat4::from_scale_rotation_translation(
+            scale,
+            glam::Quat::from_mat3(&rotation),
+            translation,
+        );
+    }
+
+    /// Replace the scale with a uniform `scale`, keeping position and
+    /// rotation
+    pub fn set_scale(&mut self, scale: f32) {
+        let (_, rotation, translation) = self.model_matrix.to_scale_rotation_translation();
+        self.model_matrix = glam::Mat4::from_scale_rotation_translation(
+            glam::Vec3::splat(scale),
+            rotation,
+            translation,
+        );
+    }
+
     pub fn from_position_rotation(
         position: glam::Vec3,
         rotation: glam::Mat3,
         scale: f32,
         color: glam::Vec4,
     ) -> Instance {
-        let model_matrix = glam::Mat4 {
-            x_axis: glam::Vec4::new(
-                rotation.x_axis.x * scale,
-                rotation.x_axis.y,
-                rotation.x_axis.z,
-                0.0,
-            ),
-            y_axis: glam::Vec4::new(
-                rotation.y_axis.x,
-                rotation.y_axis.y * scale,
-                rotation.y_axis.z,
-                0.0,
-            ),
-            z_axis: glam::Vec4::new(
-                rotation.z_axis.x,
-                rotation.z_axis.y,
-                rotation.z_axis.z * scale,
-                0.0,
-            ),
-            w_axis: glam::Vec4::new(position.x, position.y, position.z, 1.0),
-        };
+        let model_matrix = glam::Mat4::from_scale_rotation_translation(
+            glam::Vec3::splat(scale),
+            glam::Quat::from_mat3(&rotation),
+            position,
+        );
 
         Instance {
             model_matrix,
             color,
+            uv_region: Instance::FULL_UV_REGION,
+        }
+    }
+}
+
+/// Options fixed at renderer construction
+#[derive(Debug, Clone)]
+pub struct RendererConfig {
+    /// Directory to record a wgpu API trace into, for attaching to bug
+    /// reports. Requires building with the `trace` feature and is ignored
+    /// (with a warning) otherwise, and on wasm.
+    pub trace_path: Option<std::path::PathBuf>,
+    /// Latitude bands of the `MeshType::Sphere` mesh
+    pub sphere_divisions: usize,
+    /// Outline MSAA sample count, see `Renderer::set_outline_msaa`
+    pub sample_count: u32,
+    pub clear_color: wgpu::Color,
+    /// Falls back to `Fifo` when the surface doesn't support it, see
+    /// `Renderer::set_present_mode`
+    pub present_mode: wgpu::PresentMode,
+    /// See `Renderer::max_surface_dimension`. Only set by default on wasm,
+    /// where browsers such as Firefox fail to create WebGL2 surfaces larger
+    /// than 2048 pixels; native output would just be blurrier.
+    pub max_surface_dimension: Option<u32>,
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            trace_path: None,
+            sphere_divisions: 10,
+            sample_count: 1,
+            clear_color: DEFAULT_CLEAR_COLOR,
+            present_mode: wgpu::PresentMode::Fifo,
+            max_surface_dimension: if cfg!(target_arch = "wasm32") {
+                Some(2048)
+            } else {
+                None
+            },
+        }
+    }
+}
+
+impl RendererConfig {
+    fn wgpu_trace(&self) -> wgpu::Trace {
+        match &self.trace_path {
+            #[cfg(all(feature = "trace", not(target_arch = "wasm32")))]
+            Some(path) => wgpu::Trace::Directory(path.clone()),
+            #[cfg(not(all(feature = "trace", not(target_arch = "wasm32"))))]
+            Some(path) => {
+                log::warn!(
+                    "Ignoring trace path {}, built without the `trace` feature",
+                    path.display()
+                );
+                wgpu::Trace::Off
+            }
+            None => wgpu::Trace::Off,
         }
     }
 }
 
+/// Intermediate color target the scene is drawn into when the render scale
+/// is not 1, then stretched onto the surface
+pub struct ScaledTarget {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub bind_group: wgpu::BindGroup,
+}
+
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum OutlineMode {
+    /// Edge lines drawn over a slightly inflated copy of each mesh
+    #[default]
+    Hull,
+    /// Silhouettes and creases found by a screen-space Sobel filter over
+    /// scene normals and depth
+    EdgeDetect,
+}
+
+/// Which passes `Renderer::render` draws, see `Renderer::set_render_mode`
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum RenderMode {
+    /// Filled faces, plus outlines for highlighted and outlined commands
+    #[default]
+    Solid,
+    /// Every command's edges in its own color, with no filled faces
+    Wireframe,
+    /// Filled faces with every command outlined in `outline_mode`
+    SolidWithOutline,
+}
+
+/// Normal/depth target and pipelines for `OutlineMode::EdgeDetect`
+pub struct EdgeDetectTarget {
+    pub normal_depth_pipeline: wgpu::RenderPipeline,
+    pub edge_pipeline: wgpu::RenderPipeline,
+    /// Outline color `edge_pipeline` was built with
+    pub color: glam::Vec4,
+    pub view: wgpu::TextureView,
+    pub bind_group: wgpu::BindGroup,
+}
+
+/// Pipelines for `Renderer::set_depth_prepass`: opaque depth first, then
+/// color only where the depth matches exactly
+pub struct DepthPrepass {
+    pub depth_pipeline: wgpu::RenderPipeline,
+    pub color_pipeline: wgpu::RenderPipeline,
+}
+
+/// Reference grid drawn under the scene, see `Renderer::enable_grid`.
+/// Unaffected by `commands`.
+pub struct Grid {
+    pub lines: Mesh,
+    /// The two lines through the origin, drawn in `axis_color`
+    pub axes: Mesh,
+    pub color: glam::Vec4,
+    pub axis_color: glam::Vec4,
+}
+
+/// Screen corner for `Renderer::draw_axis_gizmo`
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    #[default]
+    BottomLeft,
+    BottomRight,
+}
+
+/// Arrow meshes and uniforms for `Renderer::draw_axis_gizmo`, separate from
+/// the scene's so drawing the gizmo doesn't overwrite scene instances
+pub struct AxisGizmo {
+    pub shaft: Mesh,
+    pub head: Mesh,
+    pub uniform_buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+/// One-pixel object-id target for `Renderer::pick`. The pick projection
+/// zooms onto the pixel under the cursor, so only that pixel is drawn.
+pub struct PickTarget {
+    pub pipeline: wgpu::RenderPipeline,
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub depth_view: wgpu::TextureView,
+    pub uniform_buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    pub readback_buffer: wgpu::Buffer,
+}
+
+/// Multisampled targets and pipelines for anti-aliased outlines, see
+/// `Renderer::set_outline_msaa`. Edges are drawn against a multisampled copy
+/// of the solid depth, resolved, then composited over the 1x scene.
+pub struct OutlineMsaa {
+    pub depth_pipeline: wgpu::RenderPipeline,
+    pub edge_pipeline: wgpu::RenderPipeline,
+    pub composite_pipeline: wgpu::RenderPipeline,
+    pub color_view: wgpu::TextureView,
+    pub depth_view: wgpu::TextureView,
+    pub resolve_view: wgpu::TextureView,
+    pub resolve_bind_group: wgpu::BindGroup,
+}
+
 pub struct Renderer {
-    pub adapter: wgpu::Adapter,
+    /// `None` when built with `from_device`, format and surface support is
+    /// then limited to what wgpu guarantees
+    pub adapter: Option<wgpu::Adapter>,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub surface: wgpu::Surface<'static>,
     pub surface_config: wgpu::SurfaceConfiguration,
     pub solid_pipeline: wgpu::RenderPipeline,
+    /// Solid pipeline without depth writes, for commands with alpha < 1
+    pub transparent_pipeline: wgpu::RenderPipeline,
+    /// Set while opaque solids get a depth prepass, see `set_depth_prepass`
+    pub depth_prepass: Option<DepthPrepass>,
     pub outline_pipeline: wgpu::RenderPipeline,
+    /// Outline pipeline drawing from the solid instances, which the shader
+    /// whitens and inflates. Used by `render_solid_with_edges`.
+    pub edge_pipeline: wgpu::RenderPipeline,
     pub uniform_buffer: wgpu::Buffer,
+    /// Point lights, a storage buffer where supported and otherwise a
+    /// uniform buffer of `MAX_UNIFORM_POINT_LIGHTS`
+    pub point_light_buffer: wgpu::Buffer,
     pub uniform_bind_group: wgpu::BindGroup,
+    pub texture_bind_group_layout: wgpu::BindGroupLayout,
+    pub texture_sampler: wgpu::Sampler,
+    pub default_texture_bind_group: wgpu::BindGroup,
+    pub texture_bind_groups: HashMap<MeshType, wgpu::BindGroup>,
     pub depth_texture: wgpu::Texture,
     pub depth_texture_view: wgpu::TextureView,
+    pub uniforms: Uniforms,
+    /// Eye position from the last `update_uniforms`, translucent commands
+    /// are drawn back-to-front from here
+    pub camera_position: glam::Vec3,
+    /// View rotation from the last `update_uniforms`, for the axis gizmo
+    pub view_rotation: glam::Mat3,
     pub commands: Vec<DrawCommand>,
-    pub meshes: HashMap<MeshType, Mesh>,
-    pub enable_outlines: bool,
+    /// Background the scene is drawn over
+    pub clear_color: wgpu::Color,
+    /// Commands drawn every frame until removed, see `add_command`. Appended
+    /// to `commands` when `render` starts.
+    pub retained_commands: RetainedCommands,
+    /// Batches drawn every frame until removed, see `add_instanced`
+    pub instance_batches: RetainedCommands<InstanceBatch>,
+    /// Ordered so meshes are drawn in the same order every frame, which
+    /// keeps overlapping translucent objects from flickering
+    pub meshes: BTreeMap<MeshType, Mesh>,
+    /// Skip instances whose bounding sphere is outside the view
+    pub culling_enabled: bool,
+    /// Change with `set_render_mode`; `SolidWithOutline` turns outlines on
+    /// for every command
+    pub render_mode: RenderMode,
+    /// Hull scale of outlines for commands without their own `outline_style`.
+    /// Also used by the edges of `RenderMode::SolidWithOutline` and outline
+    /// MSAA; change with `set_outline_scale`.
+    pub outline_scale: f32,
+    /// Color of every outline, including `OutlineMode::EdgeDetect` ones,
+    /// unless a command has its own `outline_style`; change with
+    /// `set_outline_color`
+    pub outline_color: glam::Vec4,
+    /// Retained commands outlined even outside `SolidWithOutline`, e.g.
+    /// the object under the cursor. Handles stay valid as other commands
+    /// come and go; removed commands are no longer outlined.
+    pub highlighted: HashSet<CommandHandle>,
+    /// While set, `render` returns immediately without acquiring a surface
+    /// texture, so the last presented frame stays on screen and commands
+    /// are kept for when rendering resumes.
+    pub paused: bool,
+    /// Scene resolution relative to the surface, e.g. 0.5 for half
+    /// resolution or 2.0 to supersample. Change with `set_render_scale`.
+    pub render_scale: f32,
+    pub scaled_target: Option<ScaledTarget>,
+    pub blit_pipeline: wgpu::RenderPipeline,
+    pub blit_sampler: wgpu::Sampler,
+    /// When set, objects whose world bounding box covers fewer pixels than
+    /// this are drawn as that box with the cube mesh instead of their own.
+    pub proxy_threshold_px: Option<f32>,
+    /// Sample count for outlines only; solids stay at 1x. Change with
+    /// `set_outline_msaa`.
+    pub outline_msaa: u32,
+    pub outline_msaa_target: Option<OutlineMsaa>,
+    pub pick_target: PickTarget,
+    pub grid: Option<Grid>,
+    /// Length of the normal lines drawn from every vertex while normal
+    /// debugging is on, see `set_normal_debug`
+    pub normal_debug_length: Option<f32>,
+    /// Normal lines of each mesh, built when first drawn
+    pub normal_debug_meshes: BTreeMap<MeshType, Mesh>,
+    pub axis_gizmo: AxisGizmo,
+    /// Corner and size in pixels of the axis gizmo for the next frame, see
+    /// `draw_axis_gizmo`. Cleared by `render` like `commands`.
+    pub axis_gizmo_placement: Option<(Corner, u32)>,
+    /// How outlines are drawn in `RenderMode::SolidWithOutline`. Change with
+    /// `set_outline_mode`. Highlighted commands always use hull outlines.
+    pub outline_mode: OutlineMode,
+    pub edge_detect_target: Option<EdgeDetectTarget>,
+    /// When set, each `render` advances the animation clock by exactly this
+    /// many seconds and real elapsed time is ignored, so the caller controls
+    /// pacing, e.g. when recording frame sequences for video export.
+    pub fixed_timestep: Option<f32>,
+    /// Seconds the last `render` advanced the animation clock by. Pass it to
+    /// `PanOrbitCamera::tick` so camera transitions and `auto_orbit` follow
+    /// `fixed_timestep` too.
+    pub frame_delta: f32,
+    /// Animation clock in seconds, advanced by every `render`
+    pub animation_time: f32,
+    last_frame_time: Option<f64>,
+    /// Largest surface width or height `resize` configures, larger windows
+    /// are scaled down to fit and look blurry. `None` is unlimited.
+    pub max_surface_dimension: Option<u32>,
+    /// Per-pass GPU timing, `None` when the adapter lacks
+    /// `Features::TIMESTAMP_QUERY`
+    pub gpu_timer: Option<GpuTimer>,
+    /// Solid instances uploaded by a frame of only retained commands, drawn
+    /// again while neither they nor the view change
+    solid_draws: Option<SolidDraws>,
+    /// Set by `scene_render_passes` while a frame draws only retained
+    /// commands
+    solid_draw_key: Option<SolidDrawKey>,
 }
 
 impl Renderer {
     pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        self.render_with_overlay(|_, _, _, _| {})
+    }
+
+    /// [`Self::render`], then `overlay` records into the same encoder after
+    /// all of the renderer's passes, drawing over the surface texture `view`,
+    /// e.g. UI from `EguiOverlay::paint`.
+    pub fn render_with_overlay(
+        &mut self,
+        overlay: impl FnOnce(&wgpu::Device, &wgpu::Queue, &mut wgpu::CommandEncoder, &wgpu::TextureView),
+    ) -> Result<(), wgpu::SurfaceError> {
+        // Nothing is acquired while paused, so there is no texture left
+        // un-presented and the swapchain is not starved in any present mode.
+        if self.paused {
+            return Ok(());
+        }
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.poll(&self.device);
+        }
         let output = self.surface.get_current_texture()?;
         let view = output
             .texture
@@ -108,537 +594,3631 @@ impl Renderer {
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
+        self.begin_timed();
 
-        self.solid_render_pass(&mut encoder, &view);
-        if self.enable_outlines {
-            self.outline_render_pass(&mut encoder, &view);
+        let scene_view = match &self.scaled_target {
+            Some(target) => target.view.clone(),
+            None => view.clone(),
+        };
+        self.scene_render_passes(&mut encoder, &scene_view);
+        if let Some(bind_group) = self.scaled_target.as_ref().map(|t| t.bind_group.clone()) {
+            self.blit_render_pass(&mut encoder, &bind_group, &view);
         }
+        overlay(&self.device, &self.queue, &mut encoder, &view);
 
-        self.queue.submit(std::iter::once(encoder.finish()));
+        self.submit_timed(encoder);
         output.present();
         self.commands.clear();
+        self.axis_gizmo_placement = None;
+        self.advance_clock();
         Ok(())
     }
 
-    pub fn resize(&mut self, window_size: glam::UVec2, camera: &impl Camera) {
-        // Update surface configuration
-        self.surface_config.width = window_size.x;
-        self.surface_config.height = window_size.y;
-        self.surface.configure(&self.device, &self.surface_config);
+    /// Time the passes recorded until the next `submit_timed`
+    fn begin_timed(&mut self) {
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.begin_frame();
+        }
+    }
 
-        // Update depth texture and uniforms
-        self.update_depth_texture(window_size);
-        self.update_uniforms(camera);
+    /// Submit `encoder`, reading back the GPU timings of its passes
+    fn submit_timed(&mut self, mut encoder: wgpu::CommandEncoder) {
+        if let Some(timer) = &self.gpu_timer {
+            timer.resolve(&mut encoder);
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.begin_readback();
+        }
     }
 
-    pub fn solid_render_pass(
+    /// GPU time of each render pass in the most recently timed frame, a
+    /// frame or two behind. `None` without `Features::TIMESTAMP_QUERY`.
+    pub fn last_frame_timings(&self) -> Option<FrameTimings> {
+        self.gpu_timer.as_ref()?.last_timings.clone()
+    }
+
+    /// Add the retained commands to `commands` and draw the scene into
+    /// `scene_view`, which must be at the scaled scene resolution
+    fn scene_render_passes(
         &mut self,
         encoder: &mut wgpu::CommandEncoder,
-        view: &wgpu::TextureView,
+        scene_view: &wgpu::TextureView,
     ) {
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Render Pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color {
-                        r: 0.01,
-                        g: 0.01,
-                        b: 0.01,
-                        a: 1.0,
-                    }),
-                    store: wgpu::StoreOp::Store,
-                },
-            })],
-            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &self.depth_texture_view,
-                depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.0),
-                    store: wgpu::StoreOp::Store,
-                }),
-                stencil_ops: None,
-            }),
-            occlusion_query_set: None,
-            timestamp_writes: None,
+        // Without immediate commands the frame is fully described by the
+        // retained ones and the view, so unchanged solids can be reused
+        self.solid_draw_key = self.commands.is_empty().then(|| SolidDrawKey {
+            commands_revision: self.retained_commands.revision(),
+            view_proj: self.uniforms.view_proj,
+            eye: self.camera_position,
+            viewport: glam::uvec2(self.surface_config.width, self.surface_config.height),
+            culling_enabled: self.culling_enabled,
+            proxy_threshold_px: self.proxy_threshold_px,
         });
-
-        render_pass.set_pipeline(&self.solid_pipeline);
-        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
-
-        // Draw meshes
-        let mesh_types: Vec<MeshType> = self.meshes.keys().cloned().collect();
-        for mesh_type in mesh_types {
-            match mesh_type {
-                MeshType::Cube => self.render_mesh(&mesh_type, &mut render_pass),
-                MeshType::Tetrahedron => self.render_mesh(&mesh_type, &mut render_pass),
-                MeshType::Sphere => self.render_mesh(&mesh_type, &mut render_pass),
-                _ => log::warn!(
-                    "{:?} mesh rendering has not been implemented yet",
-                    mesh_type
-                ),
+        self.commands
+            .extend(self.retained_commands.iter().map(|(handle, command)| {
+                let mut command = command.clone();
+                command.outline |= self.highlighted.contains(&handle);
+                command
+            }));
+        orient_billboards(&mut self.commands, self.view_rotation);
+        if let Some(threshold) = self.proxy_threshold_px {
+            self.apply_proxies(threshold);
+        }
+        let outlines = self.render_mode == RenderMode::SolidWithOutline;
+        if self.render_mode == RenderMode::Wireframe {
+            self.wireframe_render_pass(encoder, scene_view);
+        } else if outlines && self.edge_detect_target.is_some() {
+            self.solid_render_pass(encoder, scene_view);
+            self.edge_detect_render_pass(encoder, scene_view);
+        } else if outlines && self.outline_msaa_target.is_some() {
+            self.solid_render_pass(encoder, scene_view);
+            self.msaa_outline_render_pass(encoder, scene_view);
+        } else if outlines {
+            self.solid_with_edges_render_pass(encoder, scene_view);
+        } else {
+            self.solid_render_pass(encoder, scene_view);
+            if self.commands.iter().any(|cmd| cmd.outline) {
+                self.outline_render_pass(encoder, scene_view);
             }
         }
+        if self.normal_debug_length.is_some() {
+            self.normal_debug_render_pass(encoder, scene_view);
+        }
+        if self.grid.is_some() {
+            self.grid_render_pass(encoder, scene_view);
+        }
+        if let Some((corner, size_px)) = self.axis_gizmo_placement {
+            self.axis_gizmo_render_pass(encoder, scene_view, corner, size_px);
+        }
+        self.solid_draw_key = None;
     }
 
-    pub fn outline_render_pass(
-        &mut self,
-        encoder: &mut wgpu::CommandEncoder,
-        view: &wgpu::TextureView,
-    ) {
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Render Pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Load,
-                    store: wgpu::StoreOp::Store,
-                },
-            })],
-            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &self.depth_texture_view,
-                depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Load,
-                    store: wgpu::StoreOp::Store,
-                }),
-                stencil_ops: None,
-            }),
-            occlusion_query_set: None,
-            timestamp_writes: None,
-        });
+    /// Render the current commands into a new image at the scene
+    /// resolution (the surface size times `render_scale`), without
+    /// presenting. Commands are kept for the next `render`. Blocks until the
+    /// GPU has finished, so this is not usable on wasm. Surfaces in formats
+    /// other than 8-bit RGBA or BGRA return `RaidenError::FrameCapture`.
+    pub fn capture_frame(&mut self) -> Result<image::RgbaImage, RaidenError> {
+        let format = self.surface_config.format;
+        // `read_texture_bytes` hands BGRA back as RGBA already
+        if !matches!(
+            format,
+            wgpu::TextureFormat::Rgba8Unorm
+                | wgpu::TextureFormat::Rgba8UnormSrgb
+                | wgpu::TextureFormat::Bgra8Unorm
+                | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            return Err(RaidenError::FrameCapture(format!(
+                "unsupported surface format {format:?}"
+            )));
+        }
+        let size = self.scaled_size(glam::uvec2(
+            self.surface_config.width,
+            self.surface_config.height,
+        ));
+        let (texture, view) = create_color_target(
+            &self.device,
+            "Capture Texture",
+            size,
+            format,
+            1,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        );
+        let readback_buffer = create_readback_buffer(&self.device, size);
 
-        render_pass.set_pipeline(&self.outline_pipeline);
-        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Capture Encoder"),
+            });
+        self.begin_timed();
+        let commands = self.commands.clone();
+        self.scene_render_passes(&mut encoder, &view);
+        self.commands = commands;
+        self.submit_timed(encoder);
 
-        // Draw meshes
-        let mesh_types: Vec<MeshType> = self.meshes.keys().cloned().collect();
-        for mesh_type in mesh_types {
-            match mesh_type {
-                MeshType::Cube => self.render_outline_mesh(&mesh_type, &mut render_pass),
-                MeshType::Tetrahedron => self.render_outline_mesh(&mesh_type, &mut render_pass),
-                MeshType::Sphere => self.render_outline_mesh(&mesh_type, &mut render_pass),
-                _ => log::warn!(
-                    "{:?} mesh rendering has not been implemented yet",
-                    mesh_type
-                ),
-            }
-        }
+        let pixels = read_texture_bytes(&self.device, &self.queue, &texture, &readback_buffer);
+        image::RgbaImage::from_raw(size.x, size.y, pixels)
+            .ok_or_else(|| RaidenError::FrameCapture("readback size mismatch".to_string()))
     }
 
-    pub fn update_depth_texture(&mut self, window_size: glam::UVec2) {
-        log::debug!("Redarw depth buffer to size: {}", window_size);
-        // Depth Buffer
-        self.depth_texture = self.device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Depth Texture"),
-            size: wgpu::Extent3d {
-                width: window_size.x,
-                height: window_size.y,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth24Plus,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            view_formats: &[wgpu::TextureFormat::Depth24Plus],
-        });
-        self.depth_texture_view = self
-            .depth_texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+    /// [`Self::capture_frame`] and save it to `path`, in the format its
+    /// extension names (PNG is supported)
+    pub fn save_screenshot(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<(), RaidenError> {
+        self.capture_frame()?.save(path)?;
+        Ok(())
     }
 
-    pub fn update_uniforms(&mut self, camera: &impl Camera) {
-        let uniforms = Uniforms {
-            view_proj: camera.proj_matrix() * camera.view_matrix(),
-        };
-        log::trace!("Uniforms: {}", uniforms.view_proj);
-        self.queue
-            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+    /// Write the retained commands, then any immediate `commands` not yet
+    /// rendered, to `path` as JSON. Instance batches are not included.
+    #[cfg(feature = "serde")]
+    pub fn save_scene(&self, path: impl AsRef<std::path::Path>) -> Result<(), RaidenError> {
+        let path = path.as_ref();
+        let error = |message: String| RaidenError::Scene(format!("{}: {message}", path.display()));
+        let commands: Vec<&DrawCommand> = self
+            .retained_commands
+            .iter()
+            .map(|(_, command)| command)
+            .chain(&self.commands)
+            .collect();
+        let json = serde_json::to_string(&commands).map_err(|err| error(err.to_string()))?;
+        std::fs::write(path, json).map_err(|err| error(err.to_string()))
     }
 
-    pub async fn from_winit_window(window: Arc<winit::window::Window>) -> anyhow::Result<Self> {
-        let window_size = window.inner_size();
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-            #[cfg(not(target_arch = "wasm32"))]
-            backends: wgpu::Backends::PRIMARY,
-            #[cfg(target_arch = "wasm32")]
-            backends: wgpu::Backends::all(),
-            ..Default::default()
-        });
-        let surface = instance
-            .create_surface(window.clone())
-            .expect("Failed to create surface");
-        log::debug!("Surface created.");
+    /// Add the commands saved by [`Self::save_scene`] as retained commands,
+    /// returning their handles in file order. Commands already added are
+    /// kept; call `clear_commands` first to replace the scene.
+    /// `MeshType::Custom` ids are stored as-is, so custom meshes must be
+    /// registered under the same ids again.
+    #[cfg(feature = "serde")]
+    pub fn load_scene(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<Vec<CommandHandle>, RaidenError> {
+        let path = path.as_ref();
+        let error = |message: String| RaidenError::Scene(format!("{}: {message}", path.display()));
+        let json = std::fs::read_to_string(path).map_err(|err| error(err.to_string()))?;
+        let commands: Vec<DrawCommand> =
+            serde_json::from_str(&json).map_err(|err| error(err.to_string()))?;
+        Ok(commands
+            .into_iter()
+            .map(|command| self.add_command(command))
+            .collect())
+    }
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                compatible_surface: Some(&surface),
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                force_fallback_adapter: false,
+    /// Step `animation_time` by `fixed_timestep` if set, otherwise by the
+    /// wall-clock time since the previous frame
+    fn advance_clock(&mut self) {
+        let now = now_secs();
+        let elapsed = self.last_frame_time.map_or(0.0, |last| (now - last) as f32);
+        self.last_frame_time = Some(now);
+        self.frame_delta = self.fixed_timestep.unwrap_or(elapsed);
+        self.animation_time += self.frame_delta;
+    }
+
+    /// Swap commands smaller than `threshold` pixels on screen for cube
+    /// commands covering their world bounding box. Only used by `render`,
+    /// which clears the commands afterwards.
+    fn apply_proxies(&mut self, threshold: f32) {
+        let local_bounds: HashMap<MeshType, (glam::Vec3, glam::Vec3)> = self
+            .meshes
+            .iter()
+            .map(|(mesh_type, mesh)| (*mesh_type, mesh.bounds()))
+            .collect();
+        let viewport = glam::vec2(
+            self.surface_config.width as f32,
+            self.surface_config.height as f32,
+        );
+        apply_proxies(
+            &mut self.commands,
+            &local_bounds,
+            self.uniforms.view_proj,
+            viewport,
+            threshold,
+        );
+    }
+
+    /// Closest retained command hit by a world-space ray, with the hit
+    /// parameter as returned by [`Mesh::raycast`]
+    pub fn pick_precise(
+        &self,
+        ray_origin: glam::Vec3,
+        ray_dir: glam::Vec3,
+    ) -> Option<(CommandHandle, f32)> {
+        self.retained_commands
+            .iter()
+            .filter_map(|(handle, command)| {
+                let mesh = self.meshes.get(&command.mesh_type)?;
+                let t = mesh.raycast(ray_origin, ray_dir, command.instance.model_matrix)?;
+                Some((handle, t))
             })
-            .await?;
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
 
-        let surface_caps = surface.get_capabilities(&adapter);
-        let surface_format = surface_caps
-            .formats
+    /// Nearest retained command whose transformed mesh bounds a world-space
+    /// ray hits, with the distance along `dir` to the box. Coarser than
+    /// `pick` and `pick_precise` but needs neither a readback nor the
+    /// triangles, e.g. `camera.screen_ray(cursor, viewport)` for clicks.
+    pub fn raycast(&self, origin: glam::Vec3, dir: glam::Vec3) -> Option<(CommandHandle, f32)> {
+        let dir = dir.normalize();
+        self.retained_commands
             .iter()
-            .find(|f| f.is_srgb())
-            .copied()
-            .unwrap_or(surface_caps.formats[0]);
-        let surface_config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            width: window_size.width,
-            height: window_size.height,
-            present_mode: wgpu::PresentMode::Fifo,
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
-            desired_maximum_frame_latency: 2,
-        };
+            .filter_map(|(handle, command)| {
+                let mesh = self.meshes.get(&command.mesh_type)?;
+                // An affine transform keeps `t` the same, so test in model space
+                let inverse = command.instance.model_matrix.inverse();
+                let (min, max) = mesh.bounds();
+                let t = mesh::ray_hits_aabb(
+                    inverse.transform_point3(origin),
+                    inverse.transform_vector3(dir),
+                    min,
+                    max,
+                )?;
+                Some((handle, t.max(0.0)))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
 
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor {
+    /// The retained command drawn at surface pixel (`x`, `y`), see
+    /// `add_command`. Immediate commands hide what is behind them but can't
+    /// be picked, nor can retained commands past the first
+    /// [`MAX_PICKABLE_COMMANDS`]. Renders an object-id pass with the last `update_uniforms`
+    /// view and blocks on the readback, so this is not usable on wasm.
+    pub fn pick(&self, x: u32, y: u32) -> Option<CommandHandle> {
+        let size = glam::uvec2(self.surface_config.width, self.surface_config.height);
+        if x >= size.x || y >= size.y {
+            return None;
+        }
+        let mut uniforms = self.uniforms;
+        uniforms.view_proj = pixel_projection(size, glam::uvec2(x, y)) * uniforms.view_proj;
+        let (instances, handles) =
+            pick_instances(&self.commands, &self.retained_commands, self.view_rotation);
+        let id = render_pick(
+            &self.device,
+            &self.queue,
+            &self.pick_target,
+            &self.meshes,
+            uniforms,
+            &instances,
+        );
+        (id as usize)
+            .checked_sub(1)
+            .and_then(|index| handles.get(index))
+            .copied()
+    }
+
+    pub fn has_mesh(&self, mesh_type: MeshType) -> bool {
+        self.meshes.contains_key(&mesh_type)
+    }
+
+    /// Add a mesh built outside the renderer, e.g. with
+    /// [`Mesh::new_tube_along_path`], returning the type to draw it with.
+    pub fn add_mesh(&mut self, mesh: Mesh) -> MeshType {
+        let mesh_type = mesh.mesh_type();
+        self.meshes.insert(mesh_type, mesh);
+        self.solid_draws = None;
+        self.normal_debug_meshes.remove(&mesh_type);
+        mesh_type
+    }
+
+    /// Upload caller-generated geometry, see [`Mesh::from_data`], and return
+    /// the new `MeshType::Custom` to draw it with
+    pub fn register_mesh(
+        &mut self,
+        vertices: &[Vertex],
+        indices: &[u32],
+        edges: &[u32],
+    ) -> MeshType {
+        let mesh = Mesh::from_data(&self.device, vertices, indices, edges);
+        self.add_mesh(mesh)
+    }
+
+    pub fn registered_mesh_types(&self) -> Vec<MeshType> {
+        self.meshes.keys().copied().collect()
+    }
+
+    /// Instances per draw call under WebGL2's buffer size limit, or the
+    /// device's if lower. Larger meshes are drawn in several calls.
+    pub fn max_instances_webgl(&self) -> usize {
+        let webgl = wgpu::Limits::downlevel_webgl2_defaults();
+        let device = self.device.limits();
+        mesh::max_instances_per_buffer(webgl.max_buffer_size.min(device.max_buffer_size))
+    }
+
+    fn texture_format_features(&self, format: wgpu::TextureFormat) -> wgpu::TextureFormatFeatures {
+        match &self.adapter {
+            Some(adapter) => adapter.get_texture_format_features(format),
+            None => format.guaranteed_format_features(self.device.features()),
+        }
+    }
+
+    /// Shrink every mesh's instance buffers back to their default capacity
+    pub fn reset_all_buffers(&mut self) {
+        for mesh in self.meshes.values_mut() {
+            mesh.reset_instance_capacity(&self.device);
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        // Don't count the paused time as one long frame
+        self.last_frame_time = None;
+    }
+
+    /// Keep drawing `command` every frame until it is removed, unlike
+    /// `commands`, which are cleared after each `render`. Frames with only
+    /// retained commands skip gathering and uploading solid instances while
+    /// the commands and the view are unchanged.
+    pub fn add_command(&mut self, command: DrawCommand) -> CommandHandle {
+        self.retained_commands.insert(command)
+    }
+
+    pub fn remove_command(&mut self, handle: CommandHandle) -> Option<DrawCommand> {
+        self.highlighted.remove(&handle);
+        self.retained_commands.remove(handle)
+    }
+
+    /// Drop every immediate and retained command and every instance batch,
+    /// e.g. before loading another scene
+    pub fn clear_commands(&mut self) {
+        self.commands.clear();
+        self.retained_commands.clear();
+        self.instance_batches.clear();
+        self.highlighted.clear();
+    }
+
+    /// Number of commands the next `render` draws, immediate and retained
+    pub fn command_count(&self) -> usize {
+        self.commands.len() + self.retained_commands.len()
+    }
+
+    /// Draw `instances` of `mesh_type` every frame until removed with
+    /// `remove_instanced`. Batches skip culling, sorting, outlines and
+    /// picking, and are always drawn as opaque. Each batch has its own
+    /// instance buffer, uploaded here and in `update_instanced` only.
+    pub fn add_instanced(&mut self, mesh_type: MeshType, instances: Vec<Instance>) -> BatchHandle {
+        BatchHandle(self.instance_batches.insert(InstanceBatch::new(
+            &self.device,
+            &self.queue,
+            mesh_type,
+            instances,
+        )))
+    }
+
+    /// Replace and upload the instances of the batch behind `handle`.
+    /// Returns `false` if it has been removed.
+    pub fn update_instanced(&mut self, handle: BatchHandle, instances: Vec<Instance>) -> bool {
+        match self.instance_batches.get_mut(handle.0) {
+            Some(batch) => {
+                batch.set_instances(&self.device, &self.queue, instances);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove_instanced(&mut self, handle: BatchHandle) -> Option<InstanceBatch> {
+        self.instance_batches.remove(handle.0)
+    }
+
+    /// Replace the command behind `handle`. Returns `false` if it has been
+    /// removed.
+    pub fn update_command(&mut self, handle: CommandHandle, command: DrawCommand) -> bool {
+        match self.retained_commands.get_mut(handle) {
+            Some(retained) => {
+                *retained = command;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move the retained command behind `handle` in place. Returns `false`
+    /// if it has been removed.
+    pub fn set_instance_transform(
+        &mut self,
+        handle: CommandHandle,
+        position: glam::Vec3,
+        rotation: glam::Mat3,
+        scale: f32,
+    ) -> bool {
+        let Some(command) = self.retained_commands.get_mut(handle) else {
+            return false;
+        };
+        // Rotation last, as the old one can't be recovered from a zero scale
+        command.instance.set_scale(scale);
+        command.instance.set_rotation(rotation);
+        command.instance.set_position(position);
+        true
+    }
+
+    /// Recolor the retained command behind `handle`. Returns `false` if it
+    /// has been removed.
+    pub fn set_instance_color(&mut self, handle: CommandHandle, color: glam::Vec4) -> bool {
+        let Some(command) = self.retained_commands.get_mut(handle) else {
+            return false;
+        };
+        command.instance.color = color;
+        true
+    }
+
+    pub fn extend_commands(
+        &mut self,
+        commands: impl IntoIterator<Item = DrawCommand>,
+    ) -> Range<CommandId> {
+        let start = self.commands.len();
+        self.commands.extend(commands);
+        start..self.commands.len()
+    }
+
+    /// Add a lattice of `mesh_type` laid out like [`commands::mesh_grid`] as
+    /// one instance batch, drawn every frame like `add_instanced` until
+    /// the returned handle is passed to `remove_instanced`
+    pub fn add_mesh_grid(
+        &mut self,
+        mesh_type: MeshType,
+        counts: glam::UVec3,
+        spacing: glam::Vec3,
+        origin: glam::Vec3,
+        scale: f32,
+        color: glam::Vec4,
+    ) -> BatchHandle {
+        let instances = commands::mesh_grid(mesh_type, counts, spacing, origin, scale, color)
+            .map(|command| command.instance)
+            .collect();
+        self.add_instanced(mesh_type, instances)
+    }
+
+    /// Switch between vsync (`Fifo`) and lower latency modes like
+    /// `Mailbox` or `Immediate`. Modes the surface doesn't support fall back
+    /// to `Fifo`; the mode in use is `surface_config.present_mode`.
+    pub fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) {
+        let supported = match &self.adapter {
+            Some(adapter) => self.surface.get_capabilities(adapter).present_modes,
+            None => vec![wgpu::PresentMode::Fifo],
+        };
+        self.surface_config.present_mode = supported_present_mode(&supported, present_mode);
+        self.surface.configure(&self.device, &self.surface_config);
+    }
+
+    /// Resize the surface and every size-dependent target to the window
+    /// size, scaled down to fit `max_surface_dimension` if set. Returns the
+    /// surface size, for the camera aspect, or `None` when either dimension
+    /// is 0 (e.g. minimized) and nothing was changed.
+    pub fn resize(&mut self, width: u32, height: u32) -> Option<glam::UVec2> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let scale = match self.max_surface_dimension {
+            Some(max_dim) if width.max(height) > max_dim => {
+                max_dim as f32 / width.max(height) as f32
+            }
+            _ => 1.0,
+        };
+        let surface_size = glam::uvec2(
+            ((width as f32 * scale) as u32).max(1),
+            ((height as f32 * scale) as u32).max(1),
+        );
+
+        self.surface_config.width = surface_size.x;
+        self.surface_config.height = surface_size.y;
+        self.surface.configure(&self.device, &self.surface_config);
+
+        self.update_depth_texture(surface_size);
+        self.update_scaled_target(surface_size);
+        self.update_outline_msaa_target(surface_size);
+        self.update_edge_detect_target(surface_size);
+        Some(surface_size)
+    }
+
+    /// Render the scene at `scale` times the surface resolution and stretch
+    /// it onto the surface. Non-positive or non-finite scales reset to 1.
+    pub fn set_render_scale(&mut self, scale: f32) {
+        self.render_scale = if scale.is_finite() && scale > 0.0 {
+            scale
+        } else {
+            log::warn!("Invalid render scale {scale}, using 1.0");
+            1.0
+        };
+        let surface_size = glam::uvec2(self.surface_config.width, self.surface_config.height);
+        self.update_depth_texture(surface_size);
+        self.update_scaled_target(surface_size);
+        self.update_outline_msaa_target(surface_size);
+        self.update_edge_detect_target(surface_size);
+    }
+
+    /// Switch how outlines are drawn. Falls back to `OutlineMode::Hull` if
+    /// the adapter can't render to the float normal/depth target.
+    pub fn set_outline_mode(&mut self, mode: OutlineMode) {
+        let renderable = self
+            .texture_format_features(NORMAL_DEPTH_FORMAT)
+            .allowed_usages
+            .contains(wgpu::TextureUsages::RENDER_ATTACHMENT);
+        self.outline_mode = if mode == OutlineMode::EdgeDetect && !renderable {
+            log::warn!("{NORMAL_DEPTH_FORMAT:?} is not renderable, using hull outlines");
+            OutlineMode::Hull
+        } else {
+            mode
+        };
+
+        let surface_size = glam::uvec2(self.surface_config.width, self.surface_config.height);
+        self.update_edge_detect_target(surface_size);
+    }
+
+    /// Recreate the edge-detect normal/depth target, and on first use the
+    /// pipelines, for a surface of `window_size`
+    pub fn update_edge_detect_target(&mut self, window_size: glam::UVec2) {
+        if self.outline_mode != OutlineMode::EdgeDetect {
+            self.edge_detect_target = None;
+            return;
+        }
+        let (_, view) = create_color_target(
+            &self.device,
+            "Normal Depth Target",
+            self.scaled_size(window_size),
+            NORMAL_DEPTH_FORMAT,
+            1,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        );
+        let bind_group = create_texture_bind_group(
+            "Normal Depth Bind Group",
+            &self.device,
+            &self.texture_bind_group_layout,
+            &view,
+            &self.blit_sampler,
+        );
+
+        if let Some(target) = &mut self.edge_detect_target {
+            target.view = view;
+            target.bind_group = bind_group;
+            return;
+        }
+
+        let shader = create_default_shader(&self.device);
+        let uniform_bind_group_layout = create_uniform_bind_group_layout(
+            &self.device,
+            "Normal Depth Uniforms Bind Group Layout",
+        );
+        self.edge_detect_target = Some(EdgeDetectTarget {
+            normal_depth_pipeline: create_normal_depth_pipeline(
+                &self.device,
+                &shader,
+                &uniform_bind_group_layout,
+            ),
+            edge_pipeline: create_edge_detect_pipeline(
+                &self.device,
+                &self.texture_bind_group_layout,
+                self.surface_config.format,
+                self.outline_color,
+            ),
+            color: self.outline_color,
+            view,
+            bind_group,
+        });
+    }
+
+    /// Draw scene normals and depth, then outline their discontinuities over
+    /// `view`. Expects `solid_render_pass` to have uploaded this frame's
+    /// instances; overwrites the depth texture.
+    pub fn edge_detect_render_pass(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        let frustum = self.frustum();
+        if let Some(edge_detect) = &mut self.edge_detect_target
+            && edge_detect.color != self.outline_color
+        {
+            edge_detect.edge_pipeline = create_edge_detect_pipeline(
+                &self.device,
+                &self.texture_bind_group_layout,
+                self.surface_config.format,
+                self.outline_color,
+            );
+            edge_detect.color = self.outline_color;
+        }
+        let Some(edge_detect) = &self.edge_detect_target else {
+            return;
+        };
+
+        {
+            let mut render_pass = begin_render_pass(
+                encoder,
+                "Normal Depth Render Pass",
+                &edge_detect.view,
+                &self.depth_texture_view,
+                // Background reads as very far away, so silhouettes are edges
+                Some(wgpu::Color {
+                    r: 0.0,
+                    g: 0.0,
+                    b: 0.0,
+                    a: 1.0e4,
+                }),
+                self.gpu_timer.as_mut(),
+            );
+            render_pass.set_pipeline(&edge_detect.normal_depth_pipeline);
+            render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            for mesh in self.meshes.values_mut() {
+                let instances = gather_instances(&self.commands, mesh, frustum.as_ref());
+                if !mesh.draw_reusing_instances(&mut render_pass, instances.len()) {
+                    mesh.draw(&self.device, &self.queue, &mut render_pass, &instances);
+                }
+            }
+        }
+
+        fullscreen_pass(
+            encoder,
+            "Edge Detect Render Pass",
+            &edge_detect.edge_pipeline,
+            &edge_detect.bind_group,
+            view,
+            wgpu::LoadOp::Load,
+            self.gpu_timer.as_mut(),
+        );
+    }
+
+    /// Multisample outlines with `sample_count` samples, or turn outline
+    /// MSAA off with 1. Counts the adapter can't render fall back to 1.
+    pub fn set_outline_msaa(&mut self, sample_count: u32) {
+        let color_flags = self
+            .texture_format_features(self.surface_config.format)
+            .flags;
+        let depth_flags = self
+            .texture_format_features(wgpu::TextureFormat::Depth24Plus)
+            .flags;
+        self.outline_msaa = if sample_count <= 1 {
+            1
+        } else if color_flags.sample_count_supported(sample_count)
+            && color_flags.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE)
+            && depth_flags.sample_count_supported(sample_count)
+        {
+            sample_count
+        } else {
+            log::warn!("{sample_count}x MSAA is not supported, disabling outline MSAA");
+            1
+        };
+
+        self.outline_msaa_target = None;
+        let surface_size = glam::uvec2(self.surface_config.width, self.surface_config.height);
+        self.update_outline_msaa_target(surface_size);
+    }
+
+    /// Recreate the outline MSAA targets, and on first use the pipelines,
+    /// for a surface of `window_size`
+    pub fn update_outline_msaa_target(&mut self, window_size: glam::UVec2) {
+        if self.outline_msaa == 1 {
+            self.outline_msaa_target = None;
+            return;
+        }
+        let size = self.scaled_size(window_size);
+        let format = self.surface_config.format;
+        let samples = self.outline_msaa;
+        let (_, color_view) = create_color_target(
+            &self.device,
+            "Outline MSAA Color Target",
+            size,
+            format,
+            samples,
+            wgpu::TextureUsages::RENDER_ATTACHMENT,
+        );
+        let (_, depth_view) = create_multisampled_depth_texture(&self.device, size, samples);
+        let (_, resolve_view) = create_color_target(
+            &self.device,
+            "Outline MSAA Resolve Target",
+            size,
+            format,
+            1,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        );
+        let resolve_bind_group = create_texture_bind_group(
+            "Outline MSAA Resolve Bind Group",
+            &self.device,
+            &self.texture_bind_group_layout,
+            &resolve_view,
+            &self.blit_sampler,
+        );
+
+        if let Some(target) = &mut self.outline_msaa_target {
+            target.color_view = color_view;
+            target.depth_view = depth_view;
+            target.resolve_view = resolve_view;
+            target.resolve_bind_group = resolve_bind_group;
+            return;
+        }
+
+        let shader = create_default_shader(&self.device);
+        let uniform_bind_group_layout = create_uniform_bind_group_layout(
+            &self.device,
+            "Outline MSAA Uniforms Bind Group Layout",
+        );
+        self.outline_msaa_target = Some(OutlineMsaa {
+            depth_pipeline: create_depth_only_pipeline(
+                &self.device,
+                &shader,
+                &uniform_bind_group_layout,
+                samples,
+            ),
+            edge_pipeline: create_outline_pipeline(
+                &self.device,
+                &shader,
+                &uniform_bind_group_layout,
+                format,
+                "edge_vs_main",
+                samples,
+            ),
+            composite_pipeline: create_blit_pipeline(
+                &self.device,
+                &self.texture_bind_group_layout,
+                format,
+                Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+            ),
+            color_view,
+            depth_view,
+            resolve_view,
+            resolve_bind_group,
+        });
+    }
+
+    /// Draw edges into the multisampled outline target and composite the
+    /// resolved result over `view`. Expects `solid_render_pass` to have
+    /// uploaded this frame's instances.
+    pub fn msaa_outline_render_pass(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        let frustum = self.frustum();
+        let Some(msaa) = &self.outline_msaa_target else {
+            return;
+        };
+        let mesh_types: Vec<MeshType> = self.meshes.keys().copied().collect();
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Outline MSAA Depth Pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &msaa.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: self
+                    .gpu_timer
+                    .as_mut()
+                    .and_then(|timer| timer.pass_writes("Outline MSAA Depth Pass")),
+            });
+            render_pass.set_pipeline(&msaa.depth_pipeline);
+            render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            for mesh_type in &mesh_types {
+                let Some(mesh) = self.meshes.get_mut(mesh_type) else {
+                    continue;
+                };
+                let instances = gather_instances(&self.commands, mesh, frustum.as_ref());
+                if !mesh.draw_reusing_instances(&mut render_pass, instances.len()) {
+                    mesh.draw(&self.device, &self.queue, &mut render_pass, &instances);
+                }
+            }
+        }
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Outline MSAA Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &msaa.color_view,
+                    resolve_target: Some(&msaa.resolve_view),
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Discard,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &msaa.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: self
+                    .gpu_timer
+                    .as_mut()
+                    .and_then(|timer| timer.pass_writes("Outline MSAA Render Pass")),
+            });
+            render_pass.set_pipeline(&msaa.edge_pipeline);
+            render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            for mesh_type in &mesh_types {
+                let Some(mesh) = self.meshes.get_mut(mesh_type) else {
+                    continue;
+                };
+                let instances = gather_instances(&self.commands, mesh, frustum.as_ref());
+                if !mesh.draw_edges_reusing_instances(&mut render_pass, instances.len()) {
+                    mesh.draw_edges(&self.device, &self.queue, &mut render_pass, &instances);
+                }
+            }
+        }
+
+        fullscreen_pass(
+            encoder,
+            "Outline MSAA Composite Pass",
+            &msaa.composite_pipeline,
+            &msaa.resolve_bind_group,
+            view,
+            wgpu::LoadOp::Load,
+            self.gpu_timer.as_mut(),
+        );
+    }
+
+    /// Size of the scene render targets for a surface of `window_size`
+    pub fn scaled_size(&self, window_size: glam::UVec2) -> glam::UVec2 {
+        let max_dimension = self.device.limits().max_texture_dimension_2d;
+        (window_size.as_vec2() * self.render_scale)
+            .round()
+            .as_uvec2()
+            .clamp(glam::UVec2::ONE, glam::UVec2::splat(max_dimension))
+    }
+
+    pub fn update_scaled_target(&mut self, window_size: glam::UVec2) {
+        if self.render_scale == 1.0 {
+            self.scaled_target = None;
+            return;
+        }
+        let size = self.scaled_size(window_size);
+        log::debug!("Scaled render target size: {size}");
+        let (texture, view) = create_color_target(
+            &self.device,
+            "Scaled Render Target",
+            size,
+            self.surface_config.format,
+            1,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        );
+        let bind_group = create_texture_bind_group(
+            "Scaled Render Target Bind Group",
+            &self.device,
+            &self.texture_bind_group_layout,
+            &view,
+            &self.blit_sampler,
+        );
+        self.scaled_target = Some(ScaledTarget {
+            texture,
+            view,
+            bind_group,
+        });
+    }
+
+    pub fn blit_render_pass(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::BindGroup,
+        view: &wgpu::TextureView,
+    ) {
+        fullscreen_pass(
+            encoder,
+            "Blit Render Pass",
+            &self.blit_pipeline,
+            source,
+            view,
+            wgpu::LoadOp::Clear(self.clear_color),
+            self.gpu_timer.as_mut(),
+        );
+    }
+
+    pub fn solid_render_pass(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        let mut render_pass = begin_render_pass(
+            encoder,
+            "Solid Render Pass",
+            view,
+            &self.depth_texture_view,
+            Some(self.clear_color),
+            self.gpu_timer.as_mut(),
+        );
+
+        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+        self.draw_solids(&mut render_pass);
+    }
+
+    /// Draw opaque commands, then translucent ones back-to-front without
+    /// depth writes. Bind group 0 must already be set.
+    fn draw_solids(&mut self, render_pass: &mut wgpu::RenderPass<'_>) {
+        let frustum = self.frustum();
+        let batches: Vec<&InstanceBatch> = self
+            .instance_batches
+            .iter()
+            .map(|(_, batch)| batch)
+            .collect();
+        draw_solids(
+            render_pass,
+            &self.device,
+            &self.queue,
+            &mut self.meshes,
+            &self.commands,
+            &batches,
+            self.camera_position,
+            frustum.as_ref(),
+            match &self.depth_prepass {
+                Some(prepass) => SolidPipelines {
+                    depth_prepass: Some(&prepass.depth_pipeline),
+                    opaque: &prepass.color_pipeline,
+                    transparent: &self.transparent_pipeline,
+                },
+                None => SolidPipelines {
+                    depth_prepass: None,
+                    opaque: &self.solid_pipeline,
+                    transparent: &self.transparent_pipeline,
+                },
+            },
+            |mesh_type| {
+                self.texture_bind_groups
+                    .get(&mesh_type)
+                    .unwrap_or(&self.default_texture_bind_group)
+            },
+            &mut self.solid_draws,
+            self.solid_draw_key.clone(),
+        );
+    }
+
+    /// Draw solids and their edges to `view` in one pass, uploading each
+    /// mesh's instances once. Commands are kept.
+    pub fn render_solid_with_edges(&mut self, view: &wgpu::TextureView) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Solid With Edges Encoder"),
+            });
+        self.begin_timed();
+        self.solid_with_edges_render_pass(&mut encoder, view);
+        self.submit_timed(encoder);
+    }
+
+    pub fn solid_with_edges_render_pass(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        let frustum = self.frustum();
+        let mut render_pass = begin_render_pass(
+            encoder,
+            "Solid With Edges Render Pass",
+            view,
+            &self.depth_texture_view,
+            Some(self.clear_color),
+            self.gpu_timer.as_mut(),
+        );
+
+        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+        self.draw_solids(&mut render_pass);
+
+        // Per-command styles need their own hulls, the reused solid
+        // instances only carry the renderer's style
+        if self.commands.iter().any(|cmd| cmd.outline_style.is_some()) {
+            let style = OutlineStyle {
+                scale: self.outline_scale,
+                color: self.outline_color,
+            };
+            render_pass.set_pipeline(&self.outline_pipeline);
+            for mesh in self.meshes.values_mut() {
+                let instances =
+                    gather_outline_instances(&self.commands, mesh, frustum.as_ref(), style, true);
+                mesh.draw_edges(&self.device, &self.queue, &mut render_pass, &instances);
+            }
+            return;
+        }
+
+        render_pass.set_pipeline(&self.edge_pipeline);
+        for mesh in self.meshes.values_mut() {
+            let instances = gather_instances(&self.commands, mesh, frustum.as_ref());
+            if !mesh.draw_edges_reusing_instances(&mut render_pass, instances.len()) {
+                mesh.draw_edges(&self.device, &self.queue, &mut render_pass, &instances);
+            }
+        }
+    }
+
+    /// Show a grid of lines every `spacing` on the XZ plane, out to
+    /// `half_extent` from the origin. The center lines are drawn brighter,
+    /// change `Grid::axis_color` to pick another color. Spacing finer than
+    /// allowed by [`mesh::MAX_GRID_HALF_LINES`] is widened, and a
+    /// `half_extent` that isn't positive and finite hides the grid.
+    pub fn enable_grid(&mut self, half_extent: f32, spacing: f32, color: glam::Vec4) {
+        let (vertices, line_indices, axis_indices) = mesh::grid_geometry(half_extent, spacing);
+        if axis_indices.is_empty() {
+            self.grid = None;
+            return;
+        }
+        self.grid = Some(Grid {
+            lines: Mesh::from_data_as(MeshType::Plane, &self.device, &vertices, &[], &line_indices),
+            axes: Mesh::from_data_as(MeshType::Plane, &self.device, &vertices, &[], &axis_indices),
+            color,
+            axis_color: (color.truncate() * 2.0)
+                .min(glam::Vec3::ONE)
+                .extend(color.w),
+        });
+    }
+
+    pub fn disable_grid(&mut self) {
+        self.grid = None;
+    }
+
+    pub fn grid_render_pass(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        let Some(grid) = &mut self.grid else {
+            return;
+        };
+        let mut render_pass = begin_render_pass(
+            encoder,
+            "Grid Render Pass",
+            view,
+            &self.depth_texture_view,
+            None,
+            self.gpu_timer.as_mut(),
+        );
+        render_pass.set_pipeline(&self.outline_pipeline);
+        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+        for (mesh, color) in [
+            (&mut grid.lines, grid.color),
+            (&mut grid.axes, grid.axis_color),
+        ] {
+            let instance = Instance {
+                model_matrix: glam::Mat4::IDENTITY,
+                color,
+                uv_region: Instance::FULL_UV_REGION,
+            };
+            mesh.draw_edges(&self.device, &self.queue, &mut render_pass, &[instance]);
+        }
+    }
+
+    /// Draw only edges, or faces with or without outlines on everything
+    pub fn set_render_mode(&mut self, render_mode: RenderMode) {
+        self.render_mode = render_mode;
+    }
+
+    /// Clear `view` and draw the edges of every visible command in the
+    /// command's color
+    pub fn wireframe_render_pass(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        let frustum = self.frustum();
+        let mut render_pass = begin_render_pass(
+            encoder,
+            "Wireframe Render Pass",
+            view,
+            &self.depth_texture_view,
+            Some(self.clear_color),
+            self.gpu_timer.as_mut(),
+        );
+        render_pass.set_pipeline(&self.outline_pipeline);
+        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+        for (mesh_type, mesh) in self.meshes.iter_mut() {
+            let instances: Vec<Instance> = self
+                .commands
+                .iter()
+                .filter(|cmd| cmd.mesh_type == *mesh_type)
+                .filter(|cmd| is_visible(frustum.as_ref(), mesh.bounding_radius, &cmd.instance))
+                .map(|cmd| cmd.instance)
+                .collect();
+            if !instances.is_empty() {
+                mesh.draw_edges(&self.device, &self.queue, &mut render_pass, &instances);
+            }
+        }
+    }
+
+    /// Draw a `length` line along the normal of every vertex of every
+    /// command, in `NORMAL_DEBUG_COLOR`
+    pub fn set_normal_debug(&mut self, enabled: bool, length: f32) {
+        if !enabled || self.normal_debug_length != Some(length) {
+            self.normal_debug_meshes.clear();
+        }
+        self.normal_debug_length = enabled.then_some(length);
+    }
+
+    pub fn normal_debug_render_pass(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        let Some(length) = self.normal_debug_length else {
+            return;
+        };
+        let frustum = self.frustum();
+        let mut instances: BTreeMap<MeshType, Vec<Instance>> = BTreeMap::new();
+        for cmd in &self.commands {
+            let Some(mesh) = self.meshes.get(&cmd.mesh_type) else {
+                continue;
+            };
+            if is_visible(frustum.as_ref(), mesh.bounding_radius, &cmd.instance) {
+                instances.entry(cmd.mesh_type).or_default().push(Instance {
+                    color: NORMAL_DEBUG_COLOR,
+                    ..cmd.instance
+                });
+            }
+        }
+
+        let mut render_pass = begin_render_pass(
+            encoder,
+            "Normal Debug Render Pass",
+            view,
+            &self.depth_texture_view,
+            None,
+            self.gpu_timer.as_mut(),
+        );
+        render_pass.set_pipeline(&self.outline_pipeline);
+        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+        for (mesh_type, instances) in &instances {
+            let lines = self
+                .normal_debug_meshes
+                .entry(*mesh_type)
+                .or_insert_with(|| {
+                    Mesh::new_normal_lines(&self.device, &self.meshes[mesh_type], length)
+                });
+            lines.draw_edges(&self.device, &self.queue, &mut render_pass, instances);
+        }
+    }
+
+    /// Draw X, Y and Z arrows in red, green and blue in a `size_px` square
+    /// at `corner` of the next frame. Only the camera rotation is applied,
+    /// so the gizmo shows the view orientation at any zoom or pan. Like
+    /// commands, call this every frame the gizmo should be shown.
+    pub fn draw_axis_gizmo(&mut self, corner: Corner, size_px: u32) {
+        self.axis_gizmo_placement = Some((corner, size_px));
+    }
+
+    fn axis_gizmo_render_pass(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        corner: Corner,
+        size_px: u32,
+    ) {
+        let scene_size = self
+            .scaled_size(glam::uvec2(
+                self.surface_config.width,
+                self.surface_config.height,
+            ))
+            .as_vec2();
+        let size = (size_px as f32 * self.render_scale).min(scene_size.min_element());
+        let x = match corner {
+            Corner::TopLeft | Corner::BottomLeft => 0.0,
+            Corner::TopRight | Corner::BottomRight => scene_size.x - size,
+        };
+        let y = match corner {
+            Corner::TopLeft | Corner::TopRight => 0.0,
+            Corner::BottomLeft | Corner::BottomRight => scene_size.y - size,
+        };
+
+        // Arrows reach 1 from the origin, leave a margin for the heads
+        let view_proj = glam::Mat4::orthographic_rh(-1.1, 1.1, -1.1, 1.1, -2.0, 2.0)
+            * glam::Mat4::from_mat3(self.view_rotation);
+        let mut uniforms = self.uniforms;
+        uniforms.view_proj = view_proj;
+        uniforms.inv_view_proj = view_proj.inverse();
+        uniforms.fog_mode = 0;
+        uniforms.clip_plane_count = 0;
+        uniforms.point_light_count = 0;
+        let gizmo = &mut self.axis_gizmo;
+        self.queue
+            .write_buffer(&gizmo.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+
+        // Shafts from 0 to 0.8 and heads from 0.8 to 1 along each axis, built
+        // from the Y-up unit cylinder and cone
+        let arrow = |axis: glam::Vec3, radius: f32, start: f32, end: f32| Instance {
+            model_matrix: glam::Mat4::from_scale_rotation_translation(
+                glam::vec3(radius, 0.5 * (end - start), radius),
+                glam::Quat::from_rotation_arc(glam::Vec3::Y, axis),
+                axis * 0.5 * (start + end),
+            ),
+            color: axis.extend(1.0),
+            uv_region: Instance::FULL_UV_REGION,
+        };
+        let axes = [glam::Vec3::X, glam::Vec3::Y, glam::Vec3::Z];
+        let shafts = axes.map(|axis| arrow(axis, 0.04, 0.0, 0.8));
+        let heads = axes.map(|axis| arrow(axis, 0.1, 0.8, 1.0));
+
+        // Depth is cleared so the scene never hides the gizmo
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Axis Gizmo Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_texture_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: self
+                .gpu_timer
+                .as_mut()
+                .and_then(|timer| timer.pass_writes("Axis Gizmo Render Pass")),
+        });
+        render_pass.set_viewport(x, y, size, size, 0.0, 1.0);
+        render_pass.set_pipeline(&self.solid_pipeline);
+        render_pass.set_bind_group(0, &gizmo.bind_group, &[]);
+        render_pass.set_bind_group(1, &self.default_texture_bind_group, &[]);
+        gizmo
+            .shaft
+            .draw(&self.device, &self.queue, &mut render_pass, &shafts);
+        gizmo
+            .head
+            .draw(&self.device, &self.queue, &mut render_pass, &heads);
+    }
+
+    pub fn outline_render_pass(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        let mut render_pass = begin_render_pass(
+            encoder,
+            "Outline Render Pass",
+            view,
+            &self.depth_texture_view,
+            None,
+            self.gpu_timer.as_mut(),
+        );
+
+        render_pass.set_pipeline(&self.outline_pipeline);
+        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+
+        // Draw meshes
+        let mesh_types: Vec<MeshType> = self.meshes.keys().cloned().collect();
+        for mesh_type in mesh_types {
+            self.render_outline_mesh(&mesh_type, &mut render_pass);
+        }
+    }
+
+    /// Recreate the depth texture for a surface of `window_size`, scaled by
+    /// `render_scale` to match the scene target
+    pub fn update_depth_texture(&mut self, window_size: glam::UVec2) {
+        log::debug!("Redarw depth buffer to size: {}", window_size);
+        (self.depth_texture, self.depth_texture_view) =
+            create_depth_texture(&self.device, self.scaled_size(window_size));
+    }
+
+    pub fn update_uniforms(&mut self, camera: &impl Camera) {
+        self.uniforms.view_proj = camera.proj_matrix() * camera.view_matrix();
+        self.uniforms.inv_view_proj = camera.view_proj_inverse();
+        self.camera_position = camera_position(camera);
+        self.view_rotation = glam::Mat3::from_mat4(*camera.view_matrix());
+        log::trace!("Uniforms: {}", self.uniforms.view_proj);
+        self.write_uniforms();
+    }
+
+    /// Ids of the commands with alpha < 1, farthest from `eye` first. This
+    /// is the order the solid pass blends them in, from `camera_position`.
+    pub fn transparent_commands_back_to_front(&self, eye: glam::Vec3) -> Vec<CommandId> {
+        let mut transparent: Vec<(CommandId, &DrawCommand)> = self
+            .commands
+            .iter()
+            .enumerate()
+            .filter(|(_, cmd)| is_transparent(cmd))
+            .collect();
+        transparent.sort_by(|(_, a), (_, b)| back_to_front(a, b, eye));
+        transparent.into_iter().map(|(id, _)| id).collect()
+    }
+
+    pub fn set_outline_scale(&mut self, scale: f32) {
+        self.outline_scale = scale;
+        self.write_uniforms();
+    }
+
+    pub fn set_outline_color(&mut self, color: glam::Vec4) {
+        self.outline_color = color;
+        self.write_uniforms();
+    }
+
+    pub fn set_culling_enabled(&mut self, enabled: bool) {
+        self.culling_enabled = enabled;
+    }
+
+    /// View volume of the last `update_uniforms`, or `None` with culling off
+    fn frustum(&self) -> Option<Frustum> {
+        self.culling_enabled
+            .then(|| Frustum::from_view_proj(self.uniforms.view_proj))
+    }
+
+    pub fn set_clear_color(&mut self, clear_color: wgpu::Color) {
+        self.clear_color = clear_color;
+    }
+
+    pub fn set_shade_mode(&mut self, shade_mode: ShadeMode) {
+        self.uniforms.shade_mode = shade_mode as u32;
+        self.write_uniforms();
+    }
+
+    /// Color solids by instance, by vertex or by both. The vertex shader
+    /// passes both colors on and the fragment shader picks; alpha is
+    /// always the instance's, and textures multiply the result.
+    pub fn set_color_mode(&mut self, color_mode: ColorMode) {
+        self.uniforms.color_mode = color_mode as u32;
+        self.write_uniforms();
+    }
+
+    /// Rebuild the solid pipeline for `blend_mode` and switch the shader's
+    /// output to match
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        let shader = create_default_shader(&self.device);
+        let uniform_bind_group_layout =
+            create_uniform_bind_group_layout(&self.device, "Uniforms Bind Group Layout");
+        let bind_group_layouts = [&uniform_bind_group_layout, &self.texture_bind_group_layout];
+        self.solid_pipeline = create_solid_pipeline(
+            &self.device,
+            &shader,
+            &bind_group_layouts,
+            self.surface_config.format,
+            blend_mode.blend_state(),
+            true,
+            wgpu::CompareFunction::Less,
+        );
+        self.transparent_pipeline = create_solid_pipeline(
+            &self.device,
+            &shader,
+            &bind_group_layouts,
+            self.surface_config.format,
+            blend_mode.blend_state(),
+            false,
+            wgpu::CompareFunction::Less,
+        );
+        self.uniforms.blend_mode = blend_mode as u32;
+        self.write_uniforms();
+        if self.depth_prepass.is_some() {
+            self.set_depth_prepass(true);
+        }
+    }
+
+    /// Draw opaque solids twice: depth only, then color with an `Equal`
+    /// depth test, so each pixel is shaded once however much geometry
+    /// overlaps. Costs an extra pass, only worth it with heavy overdraw.
+    pub fn set_depth_prepass(&mut self, enabled: bool) {
+        if !enabled {
+            self.depth_prepass = None;
+            return;
+        }
+        let blend_mode = if self.uniforms.blend_mode == BlendMode::Premultiplied as u32 {
+            BlendMode::Premultiplied
+        } else {
+            BlendMode::Straight
+        };
+        let shader = create_default_shader(&self.device);
+        let uniform_bind_group_layout =
+            create_uniform_bind_group_layout(&self.device, "Uniforms Bind Group Layout");
+        let bind_group_layouts = [&uniform_bind_group_layout, &self.texture_bind_group_layout];
+        self.depth_prepass = Some(DepthPrepass {
+            depth_pipeline: create_depth_prepass_pipeline(
+                &self.device,
+                &shader,
+                &bind_group_layouts,
+                self.surface_config.format,
+            ),
+            color_pipeline: create_solid_pipeline(
+                &self.device,
+                &shader,
+                &bind_group_layouts,
+                self.surface_config.format,
+                blend_mode.blend_state(),
+                false,
+                wgpu::CompareFunction::Equal,
+            ),
+        });
+    }
+
+    /// Enable distance fog, or disable it with `None`
+    pub fn set_fog(&mut self, fog: Option<Fog>) {
+        match fog {
+            Some(fog) => {
+                self.uniforms.fog_mode = fog.mode as u32;
+                self.uniforms.fog_color = fog.color.extend(1.0);
+                self.uniforms.fog_start = fog.start;
+                self.uniforms.fog_end = fog.end;
+                self.uniforms.fog_density = fog.density;
+            }
+            None => self.uniforms.fog_mode = 0,
+        }
+        self.write_uniforms();
+    }
+
+    pub fn set_light(&mut self, light: DirectionalLight) {
+        self.uniforms.light_direction = light.direction.normalize_or_zero().extend(0.0);
+        self.uniforms.light_color = light.color_intensity();
+        self.write_uniforms();
+    }
+
+    /// Replace the scene's point lights. Without storage buffer support only
+    /// the first `MAX_UNIFORM_POINT_LIGHTS` are used.
+    pub fn set_point_lights(&mut self, lights: &[PointLight]) {
+        let mut lights: Vec<PointLightUniform> = lights.iter().map(Into::into).collect();
+        if !supports_storage_buffers(&self.device) && lights.len() > MAX_UNIFORM_POINT_LIGHTS {
+            log::warn!(
+                "{} point lights exceed the limit of {MAX_UNIFORM_POINT_LIGHTS} without storage buffers",
+                lights.len()
+            );
+            lights.truncate(MAX_UNIFORM_POINT_LIGHTS);
+        }
+        let capacity =
+            self.point_light_buffer.size() as usize / std::mem::size_of::<PointLightUniform>();
+        if lights.len() > capacity {
+            self.point_light_buffer =
+                create_point_light_buffer(&self.device, lights.len().next_power_of_two());
+            let layout =
+                create_uniform_bind_group_layout(&self.device, "Uniforms Bind Group Layout");
+            self.uniform_bind_group = create_uniform_bind_group(
+                &self.device,
+                &layout,
+                &self.uniform_buffer,
+                &self.point_light_buffer,
+            );
+        }
+        if !lights.is_empty() {
+            self.queue
+                .write_buffer(&self.point_light_buffer, 0, bytemuck::cast_slice(&lights));
+        }
+        self.uniforms.point_light_count = lights.len() as u32;
+        self.write_uniforms();
+    }
+
+    /// Sample `view` as the base color texture of every instance of
+    /// `mesh_type`, e.g. the output of an [`OffscreenRenderer`] for
+    /// picture-in-picture or portal effects.
+    ///
+    /// The view's texture must have been created with
+    /// `TextureUsages::TEXTURE_BINDING` and a filterable float format. Its
+    /// format does not have to match the surface format, but sRGB-ness
+    /// should: an sRGB source is decoded to linear on sampling and encoded
+    /// again by an sRGB surface, so pairing an sRGB target with a linear one
+    /// (or vice versa) visibly shifts the colors. The view must stay alive
+    /// while it is bound; re-bind after recreating the source texture.
+    pub fn set_texture_source(&mut self, mesh_type: MeshType, view: &wgpu::TextureView) {
+        let bind_group = create_texture_bind_group(
+            &mesh::label("Texture Bind Group", mesh_type),
+            &self.device,
+            &self.texture_bind_group_layout,
+            view,
+            &self.texture_sampler,
+        );
+        self.texture_bind_groups.insert(mesh_type, bind_group);
+    }
+
+    /// Upload `image` as an sRGB texture and sample it on every instance of
+    /// `mesh_type`, with the mesh's UVs. Replaces any texture source set
+    /// before.
+    pub fn set_texture(&mut self, mesh_type: MeshType, image: &image::RgbaImage) {
+        let texture = self.device.create_texture_with_data(
+            &self.queue,
+            &wgpu::TextureDescriptor {
+                label: Some(&mesh::label("Texture", mesh_type)),
+                size: wgpu::Extent3d {
+                    width: image.width(),
+                    height: image.height(),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            image.as_raw(),
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.set_texture_source(mesh_type, &view);
+    }
+
+    pub fn clear_texture_source(&mut self, mesh_type: MeshType) {
+        self.texture_bind_groups.remove(&mesh_type);
+    }
+
+    /// Hide geometry behind any of `planes`, given as `(normal, d)` with
+    /// points `p` kept where `normal.dot(p) + d >= 0`. Up to
+    /// [`MAX_CLIP_PLANES`] are used; pass an empty slice to disable clipping.
+    pub fn set_clip_planes(&mut self, planes: &[glam::Vec4]) {
+        if planes.len() > MAX_CLIP_PLANES {
+            log::warn!(
+                "Only the first {MAX_CLIP_PLANES} of {} clip planes are used",
+                planes.len()
+            );
+        }
+        let count = planes.len().min(MAX_CLIP_PLANES);
+        self.uniforms.clip_planes = [glam::Vec4::ZERO; MAX_CLIP_PLANES];
+        self.uniforms.clip_planes[..count].copy_from_slice(&planes[..count]);
+        self.uniforms.clip_plane_count = count as u32;
+        self.write_uniforms();
+    }
+
+    fn write_uniforms(&self) {
+        let uniforms = Uniforms {
+            outline_scale: self.outline_scale,
+            outline_color: self.outline_color,
+            ..self.uniforms
+        };
+        self.queue
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+    }
+
+    #[cfg(feature = "winit")]
+    pub async fn from_winit_window(
+        window: std::sync::Arc<winit::window::Window>,
+    ) -> Result<Self, RaidenError> {
+        Self::from_winit_window_with_config(window, RendererConfig::default()).await
+    }
+
+    #[cfg(feature = "winit")]
+    pub async fn from_winit_window_with_config(
+        window: std::sync::Arc<winit::window::Window>,
+        config: RendererConfig,
+    ) -> Result<Self, RaidenError> {
+        let window_size = window.inner_size();
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            #[cfg(not(target_arch = "wasm32"))]
+            backends: wgpu::Backends::PRIMARY,
+            #[cfg(target_arch = "wasm32")]
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+        let surface = instance.create_surface(window.clone())?;
+        log::debug!("Surface created.");
+
+        Self::new_with_surface_and_config(
+            surface,
+            &instance,
+            window_size.width,
+            window_size.height,
+            config,
+        )
+        .await
+    }
+
+    /// Set up a renderer drawing to `surface`, created from `instance` by
+    /// any windowing library, at `width` x `height`
+    pub async fn new_with_surface(
+        surface: wgpu::Surface<'static>,
+        instance: &wgpu::Instance,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, RaidenError> {
+        Self::new_with_surface_and_config(
+            surface,
+            instance,
+            width,
+            height,
+            RendererConfig::default(),
+        )
+        .await
+    }
+
+    pub async fn new_with_surface_and_config(
+        surface: wgpu::Surface<'static>,
+        instance: &wgpu::Instance,
+        width: u32,
+        height: u32,
+        config: RendererConfig,
+    ) -> Result<Self, RaidenError> {
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                compatible_surface: Some(&surface),
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                force_fallback_adapter: false,
+            })
+            .await?;
+
+        let surface_caps = surface.get_capabilities(&adapter);
+        let surface_format = surface_caps
+            .formats
+            .iter()
+            .find(|f| f.is_srgb())
+            .copied()
+            .unwrap_or(surface_caps.formats[0]);
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            width,
+            height,
+            present_mode: supported_present_mode(&surface_caps.present_modes, config.present_mode),
+            alpha_mode: surface_caps.alpha_modes[0],
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
                 label: Some("Device"),
-                required_features: wgpu::Features::empty(),
+                required_features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
                 required_limits: if cfg!(target_arch = "wasm32") {
                     wgpu::Limits::downlevel_webgl2_defaults()
                 } else {
                     wgpu::Limits::default()
                 },
-                memory_hints: Default::default(),
-                trace: wgpu::Trace::Off,
-            })
-            .await?;
+                memory_hints: Default::default(),
+                trace: config.wgpu_trace(),
+            })
+            .await?;
+
+        Self::from_parts(
+            Some(adapter),
+            device,
+            queue,
+            surface,
+            surface_config,
+            config,
+        )
+        .await
+    }
+
+    /// Set up a renderer sharing `device` and `queue` with the rest of an
+    /// application, e.g. an egui integration, drawing to `surface`. The
+    /// surface is configured with `surface_config` as given, and the config's
+    /// `trace_path` and `present_mode` are ignored.
+    pub async fn from_device(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        surface: wgpu::Surface<'static>,
+        surface_config: wgpu::SurfaceConfiguration,
+    ) -> Result<Self, RaidenError> {
+        Self::from_device_with_config(
+            device,
+            queue,
+            surface,
+            surface_config,
+            RendererConfig::default(),
+        )
+        .await
+    }
+
+    pub async fn from_device_with_config(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        surface: wgpu::Surface<'static>,
+        surface_config: wgpu::SurfaceConfiguration,
+        config: RendererConfig,
+    ) -> Result<Self, RaidenError> {
+        surface.configure(&device, &surface_config);
+        Self::from_parts(None, device, queue, surface, surface_config, config).await
+    }
+
+    /// Build the pipelines, buffers and meshes on an existing device
+    async fn from_parts(
+        adapter: Option<wgpu::Adapter>,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        surface: wgpu::Surface<'static>,
+        surface_config: wgpu::SurfaceConfiguration,
+        config: RendererConfig,
+    ) -> Result<Self, RaidenError> {
+        let (width, height) = (surface_config.width, surface_config.height);
+        let surface_format = surface_config.format;
+        let gpu_timer = GpuTimer::new(&device, &queue);
+
+        // Depth Buffer
+        let (depth_texture, depth_texture_view) =
+            create_depth_texture(&device, glam::uvec2(width, height));
+
+        // Buffers
+        //let aspect = window_size.width as f32 / window_size.height as f32;
+        let aspect = 2.0;
+        let proj_matrix = glam::Mat4::perspective_rh(f32::to_radians(60.0), aspect, 0.1, 1000.0);
+        let view_matrix = glam::Mat4::IDENTITY;
+        let uniforms = Uniforms::new(proj_matrix * view_matrix);
+        let uniform_buffer = create_uniform_buffer(&device, &uniforms);
+        log::debug!("Initial view: {:?}", proj_matrix * view_matrix);
+
+        // Meshes
+        let meshes = create_default_meshes(&device, config.sphere_divisions);
+
+        // Solid Bind Groups
+        let point_light_buffer = create_point_light_buffer(&device, 1);
+        let uniform_bind_group_layout =
+            create_uniform_bind_group_layout(&device, "Uniforms Bind Group Layout");
+        let uniform_bind_group = create_uniform_bind_group(
+            &device,
+            &uniform_bind_group_layout,
+            &uniform_buffer,
+            &point_light_buffer,
+        );
+
+        // Texture Bind Groups
+        let texture_bind_group_layout = create_texture_bind_group_layout(&device);
+        let texture_sampler = create_texture_sampler(&device);
+        let default_texture_bind_group = create_default_texture_bind_group(
+            &device,
+            &queue,
+            &texture_bind_group_layout,
+            &texture_sampler,
+        );
+
+        // Solid Render Pipeline
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let default_shader = create_default_shader(&device);
+        if let Some(error) = device.pop_error_scope().await {
+            return Err(RaidenError::ShaderCompilation {
+                name: "default_shader.wgsl".to_string(),
+                message: error.to_string(),
+            });
+        }
+        let solid_pipeline = create_solid_pipeline(
+            &device,
+            &default_shader,
+            &[&uniform_bind_group_layout, &texture_bind_group_layout],
+            surface_format,
+            BlendMode::default().blend_state(),
+            true,
+            wgpu::CompareFunction::Less,
+        );
+        let transparent_pipeline = create_solid_pipeline(
+            &device,
+            &default_shader,
+            &[&uniform_bind_group_layout, &texture_bind_group_layout],
+            surface_format,
+            BlendMode::default().blend_state(),
+            false,
+            wgpu::CompareFunction::Less,
+        );
+
+        // Outline Bind Groups
+        let outline_uniform_bind_group_layout =
+            create_uniform_bind_group_layout(&device, "Outline Uniforms Bind Group Layout");
+
+        // Outline Render Pipeline
+
+        // Note (mmckenna): Reuses solid uniform bind group layout
+        let outline_pipeline = create_outline_pipeline(
+            &device,
+            &default_shader,
+            &outline_uniform_bind_group_layout,
+            surface_format,
+            "vs_main",
+            1,
+        );
+        let edge_pipeline = create_outline_pipeline(
+            &device,
+            &default_shader,
+            &outline_uniform_bind_group_layout,
+            surface_format,
+            "edge_vs_main",
+            1,
+        );
+
+        let pick_target = create_pick_target(&device, &default_shader);
+
+        let axis_gizmo_uniform_buffer = create_uniform_buffer(&device, &uniforms);
+        let axis_gizmo = AxisGizmo {
+            shaft: Mesh::new_cylinder(&device, 12),
+            head: Mesh::new_cone(&device, 12, false),
+            bind_group: create_uniform_bind_group(
+                &device,
+                &uniform_bind_group_layout,
+                &axis_gizmo_uniform_buffer,
+                &point_light_buffer,
+            ),
+            uniform_buffer: axis_gizmo_uniform_buffer,
+        };
+
+        // Blit Render Pipeline
+        let blit_pipeline =
+            create_blit_pipeline(&device, &texture_bind_group_layout, surface_format, None);
+        let blit_sampler = create_blit_sampler(&device);
+
+        let mut renderer = Self {
+            adapter,
+            device,
+            queue,
+            surface,
+            surface_config,
+            depth_texture,
+            depth_texture_view,
+            solid_pipeline,
+            transparent_pipeline,
+            depth_prepass: None,
+            outline_pipeline,
+            edge_pipeline,
+            uniform_buffer,
+            point_light_buffer,
+            uniform_bind_group,
+            texture_bind_group_layout,
+            texture_sampler,
+            default_texture_bind_group,
+            texture_bind_groups: HashMap::new(),
+            uniforms,
+            camera_position: glam::Vec3::ZERO,
+            view_rotation: glam::Mat3::IDENTITY,
+            meshes,
+            commands: Vec::new(),
+            clear_color: config.clear_color,
+            retained_commands: RetainedCommands::default(),
+            instance_batches: RetainedCommands::default(),
+            culling_enabled: true,
+            render_mode: RenderMode::Solid,
+            outline_scale: OutlineStyle::default().scale,
+            outline_color: OutlineStyle::default().color,
+            highlighted: HashSet::new(),
+            paused: false,
+            render_scale: 1.0,
+            scaled_target: None,
+            blit_pipeline,
+            blit_sampler,
+            proxy_threshold_px: None,
+            outline_msaa: 1,
+            outline_msaa_target: None,
+            outline_mode: OutlineMode::Hull,
+            edge_detect_target: None,
+            pick_target,
+            grid: None,
+            normal_debug_length: None,
+            normal_debug_meshes: BTreeMap::new(),
+            axis_gizmo,
+            axis_gizmo_placement: None,
+            fixed_timestep: None,
+            frame_delta: 0.0,
+            animation_time: 0.0,
+            last_frame_time: None,
+            max_surface_dimension: config.max_surface_dimension,
+            gpu_timer,
+            solid_draws: None,
+            solid_draw_key: None,
+        };
+        if config.sample_count > 1 {
+            renderer.set_outline_msaa(config.sample_count);
+        }
+        Ok(renderer)
+    }
+
+    pub fn render_mesh(&mut self, mesh_type: &MeshType, render_pass: &mut wgpu::RenderPass<'_>) {
+        let frustum = self.frustum();
+        let mesh = match self.meshes.get_mut(mesh_type) {
+            Some(mesh) => mesh,
+            None => return,
+        };
+
+        let instances = gather_instances(&self.commands, mesh, frustum.as_ref());
+        let texture_bind_group = self
+            .texture_bind_groups
+            .get(mesh_type)
+            .unwrap_or(&self.default_texture_bind_group);
+        render_pass.set_bind_group(1, texture_bind_group, &[]);
+        mesh.draw(&self.device, &self.queue, render_pass, &instances);
+    }
+
+    pub fn render_outline_mesh(
+        &mut self,
+        mesh_type: &MeshType,
+        render_pass: &mut wgpu::RenderPass<'_>,
+    ) {
+        let frustum = self.frustum();
+        let mesh = match self.meshes.get_mut(mesh_type) {
+            Some(mesh) => mesh,
+            None => return,
+        };
+
+        let style = OutlineStyle {
+            scale: self.outline_scale,
+            color: self.outline_color,
+        };
+        let instances = gather_outline_instances(
+            &self.commands,
+            mesh,
+            frustum.as_ref(),
+            style,
+            self.render_mode == RenderMode::SolidWithOutline,
+        );
+        mesh.draw_edges(&self.device, &self.queue, render_pass, &instances);
+    }
+}
+
+/// Swap `commands` whose mesh, with model-space bounds from `local_bounds`,
+/// covers fewer than `threshold` pixels of `viewport` for cube commands
+/// covering their world bounding box
+pub(crate) fn apply_proxies(
+    commands: &mut [DrawCommand],
+    local_bounds: &HashMap<MeshType, (glam::Vec3, glam::Vec3)>,
+    view_proj: glam::Mat4,
+    viewport: glam::Vec2,
+    threshold: f32,
+) {
+    for command in commands.iter_mut() {
+        if command.mesh_type == MeshType::Cube {
+            continue;
+        }
+        let Some(&(min, max)) = local_bounds.get(&command.mesh_type) else {
+            continue;
+        };
+        let model = command.instance.model_matrix;
+        let center = model.transform_point3((min + max) * 0.5);
+        let local_half = (max - min) * 0.5;
+        let half = glam::Mat3::from_cols(
+            model.x_axis.truncate().abs(),
+            model.y_axis.truncate().abs(),
+            model.z_axis.truncate().abs(),
+        ) * local_half;
+
+        let Some(size_px) = screen_extent_px(view_proj, center - half, center + half, viewport)
+        else {
+            continue;
+        };
+        if size_px < threshold {
+            command.mesh_type = MeshType::Cube;
+            command.instance.model_matrix = glam::Mat4::from_translation(center)
+                * glam::Mat4::from_scale(half.max(glam::Vec3::splat(f32::EPSILON)));
+        }
+    }
+}
+
+/// Largest on-screen extent in pixels of the box `min..max`, or `None` if
+/// any corner is behind the camera
+fn screen_extent_px(
+    view_proj: glam::Mat4,
+    min: glam::Vec3,
+    max: glam::Vec3,
+    viewport: glam::Vec2,
+) -> Option<f32> {
+    let mut ndc_min = glam::Vec2::splat(f32::MAX);
+    let mut ndc_max = glam::Vec2::splat(f32::MIN);
+    for i in 0..8 {
+        let corner = glam::vec3(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        );
+        let clip = view_proj * corner.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.truncate().truncate() / clip.w;
+        ndc_min = ndc_min.min(ndc);
+        ndc_max = ndc_max.max(ndc);
+    }
+    let extent = (ndc_max - ndc_min) * 0.5 * viewport;
+    Some(extent.max_element())
+}
+
+/// `requested` if the surface supports it, otherwise `Fifo`, which every
+/// surface does
+fn supported_present_mode(
+    supported: &[wgpu::PresentMode],
+    requested: wgpu::PresentMode,
+) -> wgpu::PresentMode {
+    let automatic = matches!(
+        requested,
+        wgpu::PresentMode::AutoVsync | wgpu::PresentMode::AutoNoVsync
+    );
+    if automatic || supported.contains(&requested) {
+        requested
+    } else {
+        log::warn!("Present mode {requested:?} is unsupported, falling back to Fifo");
+        wgpu::PresentMode::Fifo
+    }
+}
+
+/// Draw a fullscreen triangle sampling `source` into `view`
+fn fullscreen_pass(
+    encoder: &mut wgpu::CommandEncoder,
+    label: &str,
+    pipeline: &wgpu::RenderPipeline,
+    source: &wgpu::BindGroup,
+    view: &wgpu::TextureView,
+    load: wgpu::LoadOp<wgpu::Color>,
+    timer: Option<&mut GpuTimer>,
+) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load,
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: timer.and_then(|timer| timer.pass_writes(label)),
+    });
+    render_pass.set_pipeline(pipeline);
+    render_pass.set_bind_group(0, source, &[]);
+    render_pass.draw(0..3, 0..1);
+}
+
+fn create_color_target(
+    device: &wgpu::Device,
+    label: &str,
+    size: glam::UVec2,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    usage: wgpu::TextureUsages,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width: size.x.max(1),
+            height: size.y.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    (texture, view)
+}
+
+/// Monotonic time in seconds, for the animation clock
+fn now_secs() -> f64 {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+        START
+            .get_or_init(std::time::Instant::now)
+            .elapsed()
+            .as_secs_f64()
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        web_sys::window()
+            .and_then(|window| window.performance())
+            .map_or(0.0, |performance| performance.now() / 1000.0)
+    }
+}
+
+/// Edge-detect prepass target: world normal in xyz, view depth in w
+const NORMAL_DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Object ids written by the pick pass
+const PICK_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+
+/// Background until changed with `set_clear_color`
+pub const DEFAULT_CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.01,
+    g: 0.01,
+    b: 0.01,
+    a: 1.0,
+};
+
+/// Begin a pass on `view` and `depth_view`, clearing both when `clear_color`
+/// is set and loading the previous contents otherwise. Timed as `label` by
+/// `timer` if given.
+fn begin_render_pass<'a>(
+    encoder: &'a mut wgpu::CommandEncoder,
+    label: &str,
+    view: &wgpu::TextureView,
+    depth_view: &wgpu::TextureView,
+    clear_color: Option<wgpu::Color>,
+    timer: Option<&mut GpuTimer>,
+) -> wgpu::RenderPass<'a> {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: clear_color.map_or(wgpu::LoadOp::Load, wgpu::LoadOp::Clear),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+            view: depth_view,
+            depth_ops: Some(wgpu::Operations {
+                load: if clear_color.is_some() {
+                    wgpu::LoadOp::Clear(1.0)
+                } else {
+                    wgpu::LoadOp::Load
+                },
+                store: wgpu::StoreOp::Store,
+            }),
+            stencil_ops: None,
+        }),
+        occlusion_query_set: None,
+        timestamp_writes: timer.and_then(|timer| timer.pass_writes(label)),
+    })
+}
+
+fn is_transparent(command: &DrawCommand) -> bool {
+    command.instance.color.w < 1.0
+}
+
+/// Orders commands farthest from `eye` first, the order translucent
+/// surfaces must be blended in
+fn back_to_front(a: &DrawCommand, b: &DrawCommand, eye: glam::Vec3) -> std::cmp::Ordering {
+    let distance = |cmd: &DrawCommand| cmd.instance.model_matrix.w_axis.truncate().distance(eye);
+    distance(b).total_cmp(&distance(a))
+}
+
+fn camera_position(camera: &impl Camera) -> glam::Vec3 {
+    camera.view_matrix().inverse().w_axis.truncate()
+}
+
+/// Draw `commands` with `opaque_pipeline`, then the translucent ones sorted
+/// back-to-front from `eye` with `transparent_pipeline`. Each mesh's
+/// instances are uploaded once, opaque first, and the translucent ones
+/// drawn a run at a time so the order holds across meshes. Instances
+/// outside `frustum` are skipped. `batches` are drawn opaque from their own
+/// buffers after each mesh's commands. When `key` matches the one `cache` was
+/// stored under and the meshes still hold that upload, nothing is gathered
+/// or uploaded and the cached draws are repeated.
+#[allow(clippy::too_many_arguments)]
+fn draw_solids<'a>(
+    render_pass: &mut wgpu::RenderPass<'_>,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    meshes: &mut BTreeMap<MeshType, Mesh>,
+    commands: &[DrawCommand],
+    batches: &[&InstanceBatch],
+    eye: glam::Vec3,
+    frustum: Option<&Frustum>,
+    pipelines: SolidPipelines<'_>,
+    texture_bind_group: impl Fn(MeshType) -> &'a wgpu::BindGroup,
+    cache: &mut Option<SolidDraws>,
+    key: Option<SolidDrawKey>,
+) {
+    let draws = match cache.take() {
+        Some(draws) if key.is_some() && draws.is_current(key.as_ref(), meshes) => draws,
+        _ => upload_solids(device, queue, meshes, commands, eye, frustum, key),
+    };
+
+    // With a prepass, all opaque depth is laid down before any color
+    let opaque_pipelines = pipelines
+        .depth_prepass
+        .into_iter()
+        .chain([pipelines.opaque]);
+    for pipeline in opaque_pipelines {
+        render_pass.set_pipeline(pipeline);
+        for (mesh_type, mesh) in meshes.iter_mut() {
+            render_pass.set_bind_group(1, texture_bind_group(*mesh_type), &[]);
+            if let Some(&(_, opaque_count)) = draws.uploads.get(mesh_type) {
+                mesh.draw_instance_range(render_pass, 0..opaque_count);
+            } else if let Some(instances) = draws.oversized.get(mesh_type) {
+                // Too many to upload at once, draw everything unsorted
+                mesh.draw(device, queue, render_pass, instances);
+            }
+            for batch in batches.iter().filter(|batch| batch.mesh_type == *mesh_type) {
+                mesh.draw_instance_range_from(
+                    render_pass,
+                    &batch.buffer,
+                    0..batch.uploaded_count(),
+                );
+            }
+        }
+    }
+
+    render_pass.set_pipeline(pipelines.transparent);
+    for (mesh_type, range) in &draws.transparent_runs {
+        if let Some(mesh) = meshes.get(mesh_type) {
+            render_pass.set_bind_group(1, texture_bind_group(*mesh_type), &[]);
+            mesh.draw_instance_range(render_pass, range.clone());
+        }
+    }
+
+    if draws.key.is_some() && draws.oversized.is_empty() {
+        *cache = Some(draws);
+    }
+}
+
+/// Gather the visible instances of `commands` per mesh and upload them, for
+/// [`draw_solids`]
+fn upload_solids(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    meshes: &mut BTreeMap<MeshType, Mesh>,
+    commands: &[DrawCommand],
+    eye: glam::Vec3,
+    frustum: Option<&Frustum>,
+    key: Option<SolidDrawKey>,
+) -> SolidDraws {
+    let visible = |cmd: &&DrawCommand| {
+        meshes
+            .get(&cmd.mesh_type)
+            .is_some_and(|mesh| is_visible(frustum, mesh.bounding_radius, &cmd.instance))
+    };
+    let commands: Vec<&DrawCommand> = commands.iter().filter(visible).collect();
+    let mut transparent: Vec<&DrawCommand> = commands
+        .iter()
+        .copied()
+        .filter(|cmd| is_transparent(cmd))
+        .collect();
+    transparent.sort_by(|a, b| back_to_front(a, b, eye));
+
+    // Each mesh's instances and opaque count, opaque first and then the
+    // translucent ones back-to-front, bucketed in one pass over the commands
+    let mut buckets: HashMap<MeshType, (Vec<Instance>, u32)> = HashMap::new();
+    for cmd in commands.iter().filter(|cmd| !is_transparent(cmd)) {
+        buckets
+            .entry(cmd.mesh_type)
+            .or_default()
+            .0
+            .push(cmd.instance);
+    }
+    for (instances, opaque_count) in buckets.values_mut() {
+        *opaque_count = instances.len() as u32;
+    }
+    for cmd in &transparent {
+        buckets
+            .entry(cmd.mesh_type)
+            .or_default()
+            .0
+            .push(cmd.instance);
+    }
+
+    let mut draws = SolidDraws {
+        key,
+        uploads: HashMap::new(),
+        transparent_runs: Vec::new(),
+        oversized: HashMap::new(),
+    };
+    for (mesh_type, (instances, opaque_count)) in buckets {
+        let Some(mesh) = meshes.get_mut(&mesh_type) else {
+            continue;
+        };
+        if mesh.upload_instances(device, queue, &instances) {
+            draws
+                .uploads
+                .insert(mesh_type, (mesh.instance_uploads(), opaque_count));
+        } else {
+            draws.oversized.insert(mesh_type, instances);
+        }
+    }
+
+    // Translucent runs of one mesh at a time, each continuing from where
+    // that mesh's previous run ended
+    let mut next: HashMap<MeshType, u32> = draws
+        .uploads
+        .iter()
+        .map(|(mesh_type, &(_, opaque_count))| (*mesh_type, opaque_count))
+        .collect();
+    for run in transparent.chunk_by(|a, b| a.mesh_type == b.mesh_type) {
+        let mesh_type = run[0].mesh_type;
+        let Some(start) = next.get_mut(&mesh_type) else {
+            continue;
+        };
+        let end = *start + run.len() as u32;
+        draws.transparent_runs.push((mesh_type, *start..end));
+        *start = end;
+    }
+    draws
+}
+
+/// What [`draw_solids`] depends on besides the meshes, equal between frames
+/// only when the same retained commands are drawn from the same view
+#[derive(Debug, Clone, PartialEq)]
+struct SolidDrawKey {
+    commands_revision: u64,
+    view_proj: glam::Mat4,
+    eye: glam::Vec3,
+    viewport: glam::UVec2,
+    culling_enabled: bool,
+    proxy_threshold_px: Option<f32>,
+}
+
+/// Instances [`draw_solids`] uploaded and how to draw them
+struct SolidDraws {
+    key: Option<SolidDrawKey>,
+    /// Each uploaded mesh's `instance_uploads` after the upload, and its
+    /// opaque instance count
+    uploads: HashMap<MeshType, (u64, u32)>,
+    /// Translucent instance ranges in back-to-front order
+    transparent_runs: Vec<(MeshType, Range<u32>)>,
+    /// Instances of meshes with too many to upload at once
+    oversized: HashMap<MeshType, Vec<Instance>>,
+}
+
+impl SolidDraws {
+    /// Whether these draws can be repeated for `key` without uploading
+    fn is_current(&self, key: Option<&SolidDrawKey>, meshes: &BTreeMap<MeshType, Mesh>) -> bool {
+        self.key.as_ref() == key
+            && self.uploads.iter().all(|(mesh_type, (uploads, _))| {
+                meshes
+                    .get(mesh_type)
+                    .is_some_and(|mesh| mesh.instance_uploads() == *uploads)
+            })
+    }
+}
+
+/// `instance` turned to face the view, keeping its position and its X and
+/// Y scale
+fn billboard_instance(instance: Instance, view_rotation: glam::Mat3) -> Instance {
+    let model = instance.model_matrix;
+    // Columns are the camera's right, up and backward in world space
+    let camera_basis = view_rotation.transpose();
+    Instance {
+        model_matrix: glam::Mat4::from_cols(
+            (camera_basis.x_axis * model.x_axis.truncate().length()).extend(0.0),
+            (camera_basis.y_axis * model.y_axis.truncate().length()).extend(0.0),
+            camera_basis.z_axis.extend(0.0),
+            model.w_axis,
+        ),
+        ..instance
+    }
+}
+
+/// Turn every `MeshType::Billboard` command to face the view
+fn orient_billboards(commands: &mut [DrawCommand], view_rotation: glam::Mat3) {
+    for cmd in commands
+        .iter_mut()
+        .filter(|cmd| cmd.mesh_type == MeshType::Billboard)
+    {
+        cmd.instance = billboard_instance(cmd.instance, view_rotation);
+    }
+}
+
+/// Pipelines `draw_solids` draws with, in order
+struct SolidPipelines<'a> {
+    /// Depth-only pass over the opaque instances
+    depth_prepass: Option<&'a wgpu::RenderPipeline>,
+    opaque: &'a wgpu::RenderPipeline,
+    transparent: &'a wgpu::RenderPipeline,
+}
+
+/// Whether an instance of a mesh with `bounding_radius` may be inside
+/// `frustum`. Everything is when there is no frustum to cull against.
+fn is_visible(frustum: Option<&Frustum>, bounding_radius: f32, instance: &Instance) -> bool {
+    frustum.is_none_or(|frustum| {
+        let model = instance.model_matrix;
+        let scale = [model.x_axis, model.y_axis, model.z_axis]
+            .map(|axis| axis.truncate().length())
+            .into_iter()
+            .fold(0.0, f32::max);
+        frustum.intersects_sphere(model.w_axis.truncate(), bounding_radius * scale)
+    })
+}
+
+fn gather_instances(
+    commands: &[DrawCommand],
+    mesh: &Mesh,
+    frustum: Option<&Frustum>,
+) -> Vec<Instance> {
+    commands
+        .iter()
+        .filter(|cmd| cmd.mesh_type == mesh.mesh_type())
+        .map(|cmd| cmd.instance)
+        .filter(|instance| is_visible(frustum, mesh.bounding_radius, instance))
+        .collect()
+}
+
+/// Edge hull instances of the commands flagged `outline`, or of every
+/// command with `all`, styled by their own `outline_style` or else `style`
+fn gather_outline_instances(
+    commands: &[DrawCommand],
+    mesh: &Mesh,
+    frustum: Option<&Frustum>,
+    style: OutlineStyle,
+    all: bool,
+) -> Vec<Instance> {
+    commands
+        .iter()
+        .filter(|cmd| cmd.mesh_type == mesh.mesh_type() && (cmd.outline || all))
+        .filter(|cmd| is_visible(frustum, mesh.bounding_radius, &cmd.instance))
+        .map(|cmd| {
+            let style = cmd.outline_style.unwrap_or(style);
+            let mut wire_instance = cmd.instance;
+            wire_instance.color = style.color;
+            wire_instance.model_matrix *= glam::Mat4::from_scale(glam::Vec3::splat(style.scale));
+            wire_instance
+        })
+        .collect()
+}
+
+fn create_default_meshes(
+    device: &wgpu::Device,
+    sphere_divisions: usize,
+) -> BTreeMap<MeshType, Mesh> {
+    [
+        (MeshType::Triangle, Mesh::new_triangle(device)),
+        (MeshType::Cube, Mesh::new_cube(device)),
+        (MeshType::Tetrahedron, Mesh::new_tetrahedron(device)),
+        (MeshType::Sphere, Mesh::new_sphere(device, sphere_divisions)),
+        (MeshType::Icosphere, Mesh::new_icosphere(device, 2)),
+        (MeshType::Cylinder, Mesh::new_cylinder(device, 16)),
+        (MeshType::Capsule, Mesh::new_capsule(device, 0.5, 0.5, 16)),
+        (MeshType::Arrow, Mesh::new_arrow(device)),
+        (MeshType::Billboard, Mesh::new_billboard(device)),
+        (MeshType::Cone, Mesh::new_cone(device, 16, true)),
+        (MeshType::Torus, Mesh::new_torus(device, 32, 12, 0.25)),
+        (MeshType::Plane, Mesh::new_plane(device, 10, true)),
+    ]
+    .into_iter()
+    .collect()
+}
+
+fn create_depth_texture(
+    device: &wgpu::Device,
+    size: glam::UVec2,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    create_multisampled_depth_texture(device, size, 1)
+}
+
+fn create_multisampled_depth_texture(
+    device: &wgpu::Device,
+    size: glam::UVec2,
+    sample_count: u32,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(if sample_count > 1 {
+            "Multisampled Depth Texture"
+        } else {
+            "Depth Texture"
+        }),
+        size: wgpu::Extent3d {
+            width: size.x.max(1),
+            height: size.y.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Depth24Plus,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[wgpu::TextureFormat::Depth24Plus],
+    });
+    let depth_texture_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+    (depth_texture, depth_texture_view)
+}
+
+fn create_uniform_buffer(device: &wgpu::Device, uniforms: &Uniforms) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Uniform Buffer"),
+        contents: bytemuck::cast_slice(&[*uniforms]),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
+/// WebGL2 and some downlevel devices have no storage buffers, point lights
+/// fall back to a fixed-size uniform array there
+fn supports_storage_buffers(device: &wgpu::Device) -> bool {
+    device.limits().max_storage_buffers_per_shader_stage > 0
+}
+
+/// Room for at least `capacity` point lights
+fn create_point_light_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    let (capacity, usage) = if supports_storage_buffers(device) {
+        (capacity.max(1), wgpu::BufferUsages::STORAGE)
+    } else {
+        (MAX_UNIFORM_POINT_LIGHTS, wgpu::BufferUsages::UNIFORM)
+    };
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Point Light Buffer"),
+        size: (capacity * std::mem::size_of::<PointLightUniform>()) as u64,
+        usage: usage | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_uniform_bind_group_layout(device: &wgpu::Device, label: &str) -> wgpu::BindGroupLayout {
+    let point_light_binding = if supports_storage_buffers(device) {
+        wgpu::BufferBindingType::Storage { read_only: true }
+    } else {
+        wgpu::BufferBindingType::Uniform
+    };
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some(label),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: point_light_binding,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    })
+}
 
-        // Depth Buffer
-        let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("Depth Texture"),
+fn create_uniform_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    point_light_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Uniforms Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: point_light_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+fn create_texture_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Texture Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    })
+}
+
+fn create_texture_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("Texture Sampler"),
+        address_mode_u: wgpu::AddressMode::Repeat,
+        address_mode_v: wgpu::AddressMode::Repeat,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    })
+}
+
+fn create_default_texture_bind_group(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    let default_texture = device.create_texture_with_data(
+        queue,
+        &wgpu::TextureDescriptor {
+            label: Some("Default Texture"),
             size: wgpu::Extent3d {
-                width: window_size.width.max(1),
-                height: window_size.height.max(1),
+                width: 1,
+                height: 1,
                 depth_or_array_layers: 1,
             },
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        },
+        wgpu::util::TextureDataOrder::LayerMajor,
+        &[255, 255, 255, 255],
+    );
+    create_texture_bind_group(
+        "Default Texture Bind Group",
+        device,
+        layout,
+        &default_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+        sampler,
+    )
+}
+
+/// Declaration of the point light storage buffer in default_shader.wgsl
+const POINT_LIGHTS_STORAGE: &str = "var<storage, read> point_lights: array<PointLight>;";
+
+pub(crate) fn create_default_shader(device: &wgpu::Device) -> wgpu::ShaderModule {
+    let source = shaders::get("default_shader.wgsl");
+    debug_assert!(source.contains(POINT_LIGHTS_STORAGE));
+    let source = if supports_storage_buffers(device) {
+        source.into()
+    } else {
+        let uniform =
+            format!("var<uniform> point_lights: array<PointLight, {MAX_UNIFORM_POINT_LIGHTS}>;");
+        source.replace(POINT_LIGHTS_STORAGE, &uniform).into()
+    };
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Default Shader"),
+        source: wgpu::ShaderSource::Wgsl(source),
+    })
+}
+
+fn alpha_blend_state() -> wgpu::BlendState {
+    wgpu::BlendState {
+        color: wgpu::BlendComponent {
+            operation: wgpu::BlendOperation::Add,
+            src_factor: wgpu::BlendFactor::SrcAlpha,
+            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+        },
+        alpha: wgpu::BlendComponent {
+            operation: wgpu::BlendOperation::Add,
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::Zero,
+        },
+    }
+}
+
+/// Pipeline for shaded meshes. Translucent meshes are drawn without
+/// `depth_write_enabled` so they don't hide each other.
+fn create_solid_pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    format: wgpu::TextureFormat,
+    blend: wgpu::BlendState,
+    depth_write_enabled: bool,
+    depth_compare: wgpu::CompareFunction,
+) -> wgpu::RenderPipeline {
+    let solid_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Solid Pipeline Layout"),
+        bind_group_layouts,
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(match (depth_write_enabled, depth_compare) {
+            (_, wgpu::CompareFunction::Equal) => "Depth Prepass Color Pipeline",
+            (true, _) => "Solid Pipeline",
+            (false, _) => "Transparent Pipeline",
+        }),
+        layout: Some(&solid_pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[Vertex::desc(), Instance::desc()],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(blend),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        depth_stencil: Some(wgpu::DepthStencilState {
             format: wgpu::TextureFormat::Depth24Plus,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            view_formats: &[wgpu::TextureFormat::Depth24Plus],
-        });
-        let depth_texture_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+            depth_write_enabled,
+            depth_compare,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    })
+}
 
-        // Buffers
-        //let aspect = window_size.width as f32 / window_size.height as f32;
-        let aspect = 2.0;
-        let proj_matrix = glam::Mat4::perspective_rh(f32::to_radians(60.0), aspect, 0.1, 1000.0);
-        let view_matrix = glam::Mat4::IDENTITY;
-        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Uniform Buffer"),
-            contents: bytemuck::cast_slice(&[Uniforms {
-                view_proj: proj_matrix * view_matrix,
-            }]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-        log::debug!("Initial view: {:?}", proj_matrix * view_matrix);
+/// Line list pipeline for edges. `vertex_entry_point` is `vs_main` for
+/// pre-whitened outline instances or `edge_vs_main` for solid instances.
+fn create_outline_pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    uniform_bind_group_layout: &wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+    vertex_entry_point: &str,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let outline_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Outline Pipeline Layout"),
+        bind_group_layouts: &[uniform_bind_group_layout],
+        push_constant_ranges: &[],
+    });
 
-        // Meshes
-        let meshes: HashMap<MeshType, Mesh> = [
-            (MeshType::Cube, Mesh::new_cube(&device)),
-            (MeshType::Tetrahedron, Mesh::new_tetrahedron(&device)),
-            (MeshType::Sphere, Mesh::new_sphere(&device, 10)),
-        ]
-        .into_iter()
-        .collect();
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Outline Pipeline"),
+        layout: Some(&outline_pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some(vertex_entry_point),
+            buffers: &[Vertex::desc(), Instance::desc()],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("outline_fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(alpha_blend_state()),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth24Plus,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::LineList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    })
+}
 
-        // Solid Bind Groups
-        let uniform_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Unforms Bind Group Layout"),
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-            });
-        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Uniforms Bind Group"),
-            layout: &uniform_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: uniform_buffer.as_entire_binding(),
-            }],
-        });
+fn create_blit_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("Blit Sampler"),
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    })
+}
 
-        // Solid Render Pipeline
-        let default_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Default Shader"),
-            source: wgpu::ShaderSource::Wgsl(shaders::get("default_shader.wgsl").into()),
-        });
+fn create_blit_pipeline(
+    device: &wgpu::Device,
+    texture_bind_group_layout: &wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+    blend: Option<wgpu::BlendState>,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Blit Shader"),
+        source: wgpu::ShaderSource::Wgsl(shaders::get("blit.wgsl").into()),
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Blit Pipeline Layout"),
+        bind_group_layouts: &[texture_bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Blit Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
 
-        let solid_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Solid Pipeline Layout"),
-                bind_group_layouts: &[&uniform_bind_group_layout],
-                push_constant_ranges: &[],
-            });
+fn create_normal_depth_pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    uniform_bind_group_layout: &wgpu::BindGroupLayout,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Normal Depth Pipeline Layout"),
+        bind_group_layouts: &[uniform_bind_group_layout],
+        push_constant_ranges: &[],
+    });
 
-        let solid_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Solid Pipeline"),
-            layout: Some(&solid_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &default_shader,
-                entry_point: Some("vs_main"),
-                buffers: &[Vertex::desc(), Instance::desc()],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &default_shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_format,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent {
-                            operation: wgpu::BlendOperation::Add,
-                            src_factor: wgpu::BlendFactor::SrcAlpha,
-                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                        },
-                        alpha: wgpu::BlendComponent {
-                            operation: wgpu::BlendOperation::Add,
-                            src_factor: wgpu::BlendFactor::One,
-                            dst_factor: wgpu::BlendFactor::Zero,
-                        },
-                    }),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth24Plus,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Normal Depth Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[Vertex::desc(), Instance::desc()],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("normal_depth_fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: NORMAL_DEPTH_FORMAT,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth24Plus,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            ..Default::default()
+        },
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Edge-detect pipeline drawing edges in `color`
+fn create_edge_detect_pipeline(
+    device: &wgpu::Device,
+    texture_bind_group_layout: &wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+    color: glam::Vec4,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Edge Detect Shader"),
+        source: wgpu::ShaderSource::Wgsl(shaders::get("edge_detect.wgsl").into()),
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Edge Detect Pipeline Layout"),
+        bind_group_layouts: &[texture_bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Edge Detect Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(alpha_blend_state()),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions {
+                constants: &[
+                    ("OUTLINE_RED", color.x as f64),
+                    ("OUTLINE_GREEN", color.y as f64),
+                    ("OUTLINE_BLUE", color.z as f64),
+                    ("OUTLINE_ALPHA", color.w as f64),
+                ],
+                ..Default::default()
             },
-            multiview: None,
-            cache: None,
-        });
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
 
-        // Outline Bind Groups
-        let outline_uniform_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Outline Unforms Bind Group Layout"),
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-            });
+/// Pipeline writing only the depth of solids to a pass with a color target
+/// of `format`, for `Renderer::set_depth_prepass`
+fn create_depth_prepass_pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Depth Prepass Pipeline Layout"),
+        bind_group_layouts,
+        push_constant_ranges: &[],
+    });
 
-        // Outline Render Pipeline
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Depth Prepass Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[Vertex::desc(), Instance::desc()],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("depth_fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::empty(),
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth24Plus,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            ..Default::default()
+        },
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
 
-        // Note (mmckenna): Reuses solid uniform bind group layout
-        let outline_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Outline Pipeline Layout"),
-                bind_group_layouts: &[&outline_uniform_bind_group_layout],
-                push_constant_ranges: &[],
-            });
+/// Depth-only pipeline for laying down solid depth in a multisampled pass
+fn create_depth_only_pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    uniform_bind_group_layout: &wgpu::BindGroupLayout,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Depth Only Pipeline Layout"),
+        bind_group_layouts: &[uniform_bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Depth Only Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[Vertex::desc(), Instance::desc()],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("depth_fs_main"),
+            targets: &[],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth24Plus,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            ..Default::default()
+        },
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    })
+}
 
-        let outline_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Outline Pipeline"),
-            layout: Some(&outline_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &default_shader,
-                entry_point: Some("vs_main"),
-                buffers: &[Vertex::desc(), Instance::desc()],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
+fn create_texture_bind_group(
+    label: &str,
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some(label),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
             },
-            fragment: Some(wgpu::FragmentState {
-                module: &default_shader,
-                entry_point: Some("outline_fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_format,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent {
-                            operation: wgpu::BlendOperation::Add,
-                            src_factor: wgpu::BlendFactor::SrcAlpha,
-                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                        },
-                        alpha: wgpu::BlendComponent {
-                            operation: wgpu::BlendOperation::Add,
-                            src_factor: wgpu::BlendFactor::One,
-                            dst_factor: wgpu::BlendFactor::Zero,
-                        },
-                    }),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: wgpu::TextureFormat::Depth24Plus,
-                depth_write_enabled: false,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::LineList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
             },
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
+        ],
+    })
+}
+
+pub struct OffscreenRenderer {
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    pub solid_pipeline: wgpu::RenderPipeline,
+    /// Solid pipeline without depth writes, for commands with alpha < 1
+    pub transparent_pipeline: wgpu::RenderPipeline,
+    pub outline_pipeline: wgpu::RenderPipeline,
+    pub uniform_buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    pub default_texture_bind_group: wgpu::BindGroup,
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub depth_texture: wgpu::Texture,
+    pub depth_view: wgpu::TextureView,
+    pub readback_buffer: wgpu::Buffer,
+    pub size: glam::UVec2,
+    pub format: wgpu::TextureFormat,
+    pub uniforms: Uniforms,
+    /// Eye position from the last `update_uniforms`, translucent commands
+    /// are drawn back-to-front from here
+    pub camera_position: glam::Vec3,
+    /// View rotation from the last `update_uniforms`, billboards face it
+    pub view_rotation: glam::Mat3,
+    pub commands: Vec<DrawCommand>,
+    pub clear_color: wgpu::Color,
+    pub meshes: BTreeMap<MeshType, Mesh>,
+    pub enable_outlines: bool,
+}
+
+impl OffscreenRenderer {
+    /// Headless renderer drawing into a `width` x `height` color texture of
+    /// `format`. `read_pixels` expects a 4-byte RGBA or BGRA format.
+    pub fn new(
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let size = glam::uvec2(width.max(1), height.max(1));
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Color Texture"),
+            size: wgpu::Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
             },
-            multiview: None,
-            cache: None,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
         });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let (depth_texture, depth_view) = create_depth_texture(&device, size);
+        let readback_buffer = create_readback_buffer(&device, size);
 
-        Ok(Self {
-            adapter,
+        let uniforms = Uniforms::new(glam::Mat4::IDENTITY);
+        let uniform_buffer = create_uniform_buffer(&device, &uniforms);
+        let point_light_buffer = create_point_light_buffer(&device, 1);
+        let uniform_bind_group_layout =
+            create_uniform_bind_group_layout(&device, "Offscreen Uniforms Bind Group Layout");
+        let bind_group = create_uniform_bind_group(
+            &device,
+            &uniform_bind_group_layout,
+            &uniform_buffer,
+            &point_light_buffer,
+        );
+
+        let texture_bind_group_layout = create_texture_bind_group_layout(&device);
+        let texture_sampler = create_texture_sampler(&device);
+        let default_texture_bind_group = create_default_texture_bind_group(
+            &device,
+            &queue,
+            &texture_bind_group_layout,
+            &texture_sampler,
+        );
+
+        let default_shader = create_default_shader(&device);
+        let solid_pipeline = create_solid_pipeline(
+            &device,
+            &default_shader,
+            &[&uniform_bind_group_layout, &texture_bind_group_layout],
+            format,
+            BlendMode::default().blend_state(),
+            true,
+            wgpu::CompareFunction::Less,
+        );
+        let transparent_pipeline = create_solid_pipeline(
+            &device,
+            &default_shader,
+            &[&uniform_bind_group_layout, &texture_bind_group_layout],
+            format,
+            BlendMode::default().blend_state(),
+            false,
+            wgpu::CompareFunction::Less,
+        );
+        let outline_pipeline = create_outline_pipeline(
+            &device,
+            &default_shader,
+            &uniform_bind_group_layout,
+            format,
+            "vs_main",
+            1,
+        );
+        let meshes = create_default_meshes(&device, RendererConfig::default().sphere_divisions);
+
+        Self {
             device,
             queue,
-            surface,
-            surface_config,
-            depth_texture,
-            depth_texture_view,
             solid_pipeline,
+            transparent_pipeline,
             outline_pipeline,
             uniform_buffer,
-            uniform_bind_group,
-            meshes,
+            bind_group,
+            default_texture_bind_group,
+            texture,
+            view,
+            depth_texture,
+            depth_view,
+            readback_buffer,
+            size,
+            format,
+            uniforms,
+            camera_position: glam::Vec3::ZERO,
+            view_rotation: glam::Mat3::IDENTITY,
             commands: Vec::new(),
+            clear_color: DEFAULT_CLEAR_COLOR,
+            meshes,
             enable_outlines: false,
-        })
+        }
     }
 
-    pub fn render_mesh(&mut self, mesh_type: &MeshType, render_pass: &mut wgpu::RenderPass<'_>) {
-        let mesh = match self.meshes.get_mut(mesh_type) {
-            Some(mesh) => mesh,
-            None => return,
-        };
-
-        let instances: Vec<Instance> = self
-            .commands
-            .iter()
-            .filter_map(|cmd| {
-                if &cmd.mesh_type == mesh_type {
-                    Some(cmd.instance)
-                } else {
-                    None
-                }
-            })
-            .collect();
+    /// The color target, for binding into another pass with
+    /// [`Renderer::set_texture_source`]. Created with
+    /// `TextureUsages::TEXTURE_BINDING`, so it can be sampled directly.
+    pub fn texture_view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
 
-        if instances.len() > mesh.buffers.instance_capacity {
-            mesh.realloc_instance_buffer(&self.device, instances.len());
-        }
-        // Write instances to the buffer
+    pub fn update_uniforms(&mut self, camera: &impl Camera) {
+        self.uniforms.view_proj = camera.proj_matrix() * camera.view_matrix();
+        self.uniforms.inv_view_proj = camera.view_proj_inverse();
+        self.camera_position = camera_position(camera);
+        self.view_rotation = glam::Mat3::from_mat4(*camera.view_matrix());
         self.queue.write_buffer(
-            &mesh.buffers.instance_buffer,
+            &self.uniform_buffer,
             0,
-            bytemuck::cast_slice(&instances),
+            bytemuck::cast_slice(&[self.uniforms]),
         );
+    }
 
-        render_pass.set_vertex_buffer(0, mesh.buffers.vertex_buffer.slice(..));
-        render_pass.set_vertex_buffer(1, mesh.buffers.instance_buffer.slice(..));
-        render_pass.set_index_buffer(
-            mesh.buffers.index_buffer.slice(..),
-            wgpu::IndexFormat::Uint16,
+    /// Like [`Renderer::set_blend_mode`], for the offscreen pipelines
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        let shader = create_default_shader(&self.device);
+        let uniform_bind_group_layout =
+            create_uniform_bind_group_layout(&self.device, "Offscreen Uniforms Bind Group Layout");
+        let texture_bind_group_layout = create_texture_bind_group_layout(&self.device);
+        let bind_group_layouts = [&uniform_bind_group_layout, &texture_bind_group_layout];
+        self.solid_pipeline = create_solid_pipeline(
+            &self.device,
+            &shader,
+            &bind_group_layouts,
+            self.format,
+            blend_mode.blend_state(),
+            true,
+            wgpu::CompareFunction::Less,
+        );
+        self.transparent_pipeline = create_solid_pipeline(
+            &self.device,
+            &shader,
+            &bind_group_layouts,
+            self.format,
+            blend_mode.blend_state(),
+            false,
+            wgpu::CompareFunction::Less,
+        );
+        self.uniforms.blend_mode = blend_mode as u32;
+        self.queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[self.uniforms]),
         );
-        render_pass.draw_indexed(0..mesh.indices.len() as u32, 0, 0..instances.len() as u32);
     }
 
-    pub fn render_outline_mesh(
-        &mut self,
-        mesh_type: &MeshType,
-        render_pass: &mut wgpu::RenderPass<'_>,
-    ) {
-        let mesh = match self.meshes.get_mut(mesh_type) {
-            Some(mesh) => mesh,
-            None => return,
-        };
+    /// Draw `commands` into the color texture. Unlike `Renderer::render` the
+    /// commands are kept, so the same scene can be rendered again.
+    pub fn render_to_texture(&mut self) {
+        orient_billboards(&mut self.commands, self.view_rotation);
+        let frustum = Frustum::from_view_proj(self.uniforms.view_proj);
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Offscreen Render Encoder"),
+            });
 
-        let instances: Vec<Instance> = self
-            .commands
-            .iter_mut()
-            .filter_map(|cmd| {
-                if &cmd.mesh_type == mesh_type {
-                    let mut wire_instance = cmd.instance;
-                    wire_instance.color = glam::Vec4::splat(1.0);
-                    wire_instance.model_matrix *= glam::Mat4::from_scale(glam::Vec3::splat(1.005));
-                    Some(wire_instance)
-                } else {
-                    None
-                }
+        {
+            let mut render_pass = begin_render_pass(
+                &mut encoder,
+                "Offscreen Solid Render Pass",
+                &self.view,
+                &self.depth_view,
+                Some(self.clear_color),
+                None,
+            );
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            draw_solids(
+                &mut render_pass,
+                &self.device,
+                &self.queue,
+                &mut self.meshes,
+                &self.commands,
+                &[],
+                self.camera_position,
+                Some(&frustum),
+                SolidPipelines {
+                    depth_prepass: None,
+                    opaque: &self.solid_pipeline,
+                    transparent: &self.transparent_pipeline,
+                },
+                |_| &self.default_texture_bind_group,
+                &mut None,
+                None,
+            );
+        }
+
+        if self.enable_outlines || self.commands.iter().any(|cmd| cmd.outline) {
+            let mut render_pass = begin_render_pass(
+                &mut encoder,
+                "Offscreen Outline Render Pass",
+                &self.view,
+                &self.depth_view,
+                None,
+                None,
+            );
+            render_pass.set_pipeline(&self.outline_pipeline);
+            render_pass.set_bind_group(0, &self.bind_group, &[]);
+            for mesh in self.meshes.values_mut() {
+                let instances = gather_outline_instances(
+                    &self.commands,
+                    mesh,
+                    Some(&frustum),
+                    OutlineStyle::default(),
+                    self.enable_outlines,
+                );
+                mesh.draw_edges(&self.device, &self.queue, &mut render_pass, &instances);
+            }
+        }
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Copy the color texture back to the CPU as tightly packed RGBA8 rows.
+    /// Blocks until the GPU has finished, so this is not usable on wasm.
+    pub fn read_pixels(&self) -> Vec<u8> {
+        read_texture_bytes(
+            &self.device,
+            &self.queue,
+            &self.texture,
+            &self.readback_buffer,
+        )
+    }
+
+    /// Render the current commands once per camera, returning one RGBA8
+    /// frame per camera. Textures and buffers are reused between views; only
+    /// the uniforms are re-uploaded.
+    pub fn render_views(&mut self, cameras: &[PanOrbitCamera]) -> Vec<Vec<u8>> {
+        cameras
+            .iter()
+            .map(|camera| {
+                self.update_uniforms(camera);
+                self.render_to_texture();
+                self.read_pixels()
             })
-            .collect();
+            .collect()
+    }
+}
+
+/// Retained commands `Renderer::pick` can tell apart. Ids travel through the
+/// f32 instance color, which is exact for integers up to 2^24.
+pub const MAX_PICKABLE_COMMANDS: usize = 1 << 24;
 
-        if instances.len() > mesh.buffers.edge_instance_capacity {
-            mesh.realloc_edge_instance_buffer(&self.device, instances.len());
+/// Per-mesh instances for the object-id pass, with ids carried in the
+/// instance color's red channel. Id 0 is nothing pickable and id `n` is the
+/// `n`th returned handle; `commands` only hide what is behind them.
+pub(crate) fn pick_instances(
+    commands: &[DrawCommand],
+    retained: &RetainedCommands,
+    view_rotation: glam::Mat3,
+) -> (BTreeMap<MeshType, Vec<Instance>>, Vec<CommandHandle>) {
+    let with_id = |cmd: &DrawCommand, id: usize| {
+        let instance = if cmd.mesh_type == MeshType::Billboard {
+            billboard_instance(cmd.instance, view_rotation)
+        } else {
+            cmd.instance
+        };
+        Instance {
+            color: glam::vec4(id as f32, 0.0, 0.0, 1.0),
+            ..instance
         }
-        // Write instances to the buffer
-        self.queue.write_buffer(
-            &mesh.buffers.edge_instance_buffer,
-            0,
-            bytemuck::cast_slice(&instances),
-        );
+    };
+    let mut instances: BTreeMap<MeshType, Vec<Instance>> = BTreeMap::new();
+    for cmd in commands {
+        instances
+            .entry(cmd.mesh_type)
+            .or_default()
+            .push(with_id(cmd, 0));
+    }
+    let mut handles = Vec::new();
+    for (handle, cmd) in retained.iter() {
+        let id = if handles.len() < MAX_PICKABLE_COMMANDS {
+            handles.push(handle);
+            handles.len()
+        } else {
+            0
+        };
+        instances
+            .entry(cmd.mesh_type)
+            .or_default()
+            .push(with_id(cmd, id));
+    }
+    (instances, handles)
+}
 
-        render_pass.set_vertex_buffer(0, mesh.buffers.vertex_buffer.slice(..));
-        render_pass.set_vertex_buffer(1, mesh.buffers.edge_instance_buffer.slice(..));
-        render_pass.set_index_buffer(
-            mesh.buffers.edge_index_buffer.slice(..),
-            wgpu::IndexFormat::Uint16,
-        );
-        render_pass.draw_indexed(
-            0..mesh.edge_indices.len() as u32,
-            0,
-            0..instances.len() as u32,
+/// Draw `instances` from [`pick_instances`] into the 1x1 `target` with
+/// `uniforms` and read back the id covering it
+pub(crate) fn render_pick(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    target: &PickTarget,
+    meshes: &BTreeMap<MeshType, Mesh>,
+    uniforms: Uniforms,
+    instances: &BTreeMap<MeshType, Vec<Instance>>,
+) -> u32 {
+    queue.write_buffer(&target.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Pick Encoder"),
+    });
+    {
+        let mut render_pass = begin_render_pass(
+            &mut encoder,
+            "Pick Render Pass",
+            &target.view,
+            &target.depth_view,
+            Some(wgpu::Color::TRANSPARENT),
+            None,
         );
+        render_pass.set_pipeline(&target.pipeline);
+        render_pass.set_bind_group(0, &target.bind_group, &[]);
+        for (mesh_type, instances) in instances {
+            let Some(mesh) = meshes.get(mesh_type) else {
+                continue;
+            };
+            let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&mesh::label("Pick Instance Buffer", *mesh_type)),
+                contents: bytemuck::cast_slice(instances),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+            render_pass.set_vertex_buffer(0, mesh.buffers.vertex_buffer.slice(..));
+            render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+            render_pass.set_index_buffer(
+                mesh.buffers.index_buffer.slice(..),
+                wgpu::IndexFormat::Uint32,
+            );
+            render_pass.draw_indexed(0..mesh.indices.len() as u32, 0, 0..instances.len() as u32);
+        }
     }
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let pixel = read_texture_bytes(device, queue, &target.texture, &target.readback_buffer);
+    u32::from_le_bytes([pixel[0], pixel[1], pixel[2], pixel[3]])
 }
 
-pub struct OffscreenRenderer {
-    pub device: wgpu::Device,
-    pub queue: wgpu::Queue,
-    pub solid_pipeline: wgpu::RenderPipeline,
-    pub outline_pipeline: wgpu::RenderPipeline,
-    pub uniform_buffer: wgpu::Buffer,
-    pub bind_group: wgpu::BindGroup,
-    pub texture: wgpu::Texture,
-    pub view: wgpu::TextureView,
-    pub depth_texture: wgpu::Texture,
-    pub depth_view: wgpu::TextureView,
-    pub commands: Vec<DrawCommand>,
-    pub meshes: HashMap<MeshType, Mesh>,
+/// 1x1 object-id target and pipeline for `Renderer::pick`. The pass reads
+/// no lights, so it binds a point light buffer of its own.
+pub(crate) fn create_pick_target(device: &wgpu::Device, shader: &wgpu::ShaderModule) -> PickTarget {
+    let size = glam::uvec2(1, 1);
+    let (texture, view) = create_color_target(
+        device,
+        "Pick Target",
+        size,
+        PICK_FORMAT,
+        1,
+        wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+    );
+    let (_, depth_view) = create_depth_texture(device, size);
+    let uniform_buffer = create_uniform_buffer(device, &Uniforms::new(glam::Mat4::IDENTITY));
+    let uniform_bind_group_layout =
+        create_uniform_bind_group_layout(device, "Pick Uniforms Bind Group Layout");
+    let bind_group = create_uniform_bind_group(
+        device,
+        &uniform_bind_group_layout,
+        &uniform_buffer,
+        &create_point_light_buffer(device, 1),
+    );
+
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Pick Pipeline Layout"),
+        bind_group_layouts: &[&uniform_bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Pick Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("pick_vs_main"),
+            buffers: &[Vertex::desc(), Instance::desc()],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("pick_fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: PICK_FORMAT,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth24Plus,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            ..Default::default()
+        },
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    });
+
+    PickTarget {
+        pipeline,
+        texture,
+        view,
+        depth_view,
+        uniform_buffer,
+        bind_group,
+        readback_buffer: create_readback_buffer(device, size),
+    }
+}
+
+/// Clip-space transform making the pixel at `pixel` of a `size` target fill
+/// the whole viewport
+fn pixel_projection(size: glam::UVec2, pixel: glam::UVec2) -> glam::Mat4 {
+    let size = size.as_vec2();
+    let ndc = (pixel.as_vec2() + 0.5) / size * 2.0 - 1.0;
+    // Pixel rows run down, NDC y runs up
+    let center = glam::vec2(ndc.x, -ndc.y);
+    glam::Mat4::from_cols(
+        glam::vec4(size.x, 0.0, 0.0, 0.0),
+        glam::vec4(0.0, size.y, 0.0, 0.0),
+        glam::Vec4::Z,
+        (-center * size).extend(0.0).extend(1.0),
+    )
+}
+
+fn create_readback_buffer(device: &wgpu::Device, size: glam::UVec2) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: (padded_bytes_per_row(size.x) * size.y) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    })
+}
+
+/// Copy a `texture` with 4-byte texels, e.g. RGBA8, BGRA8 or R32Uint, into
+/// `readback_buffer` (sized by [`create_readback_buffer`]) and return its
+/// tightly packed rows, with BGRA8 swizzled to RGBA8. Blocks until the GPU
+/// has finished.
+fn read_texture_bytes(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    readback_buffer: &wgpu::Buffer,
+) -> Vec<u8> {
+    let size = texture.size();
+    let unpadded_bytes_per_row = size.width * 4;
+    let padded_bytes_per_row = padded_bytes_per_row(size.width);
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Readback Encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::TexelCopyTextureInfo {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::TexelCopyBufferInfo {
+            buffer: readback_buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(size.height),
+            },
+        },
+        size,
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let slice = readback_buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |result| {
+        if let Err(e) = result {
+            log::error!("Failed to map readback buffer: {e}");
+        }
+    });
+    device
+        .poll(wgpu::PollType::Wait)
+        .expect("Failed to wait for readback");
+
+    let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * size.height) as usize);
+    {
+        let data = slice.get_mapped_range();
+        for row in data.chunks_exact(padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
+    }
+    readback_buffer.unmap();
+
+    if matches!(
+        texture.format(),
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+    ) {
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+    pixels
+}
+
+/// Row pitch for texture-to-buffer copies of 4-byte texels
+fn padded_bytes_per_row(width: u32) -> u32 {
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    (width * 4).div_ceil(align) * align
 }
@@ -0,0 +1,181 @@
+use std::sync::{Arc, OnceLock};
+
+/// Most render passes timed in one frame, later passes go untimed
+pub const MAX_TIMED_PASSES: u32 = 32;
+
+/// GPU time of one render pass
+#[derive(Debug, Clone, PartialEq)]
+pub struct PassTiming {
+    pub label: String,
+    pub micros: f64,
+}
+
+/// GPU time of each render pass of a frame, in the order they ran
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FrameTimings {
+    pub passes: Vec<PassTiming>,
+}
+
+impl FrameTimings {
+    pub fn total_micros(&self) -> f64 {
+        self.passes.iter().map(|pass| pass.micros).sum()
+    }
+
+    /// Time of the first pass labelled `label`, e.g. "Solid Render Pass"
+    pub fn get(&self, label: &str) -> Option<f64> {
+        self.passes
+            .iter()
+            .find(|pass| pass.label == label)
+            .map(|pass| pass.micros)
+    }
+}
+
+/// Timestamp queries written around render passes and read back without
+/// blocking. Results arrive a frame or more late, and frames recorded while
+/// a readback is in flight are not timed. Only passes recorded between
+/// `begin_frame` and `begin_readback` are timed, so passes a caller records
+/// into an encoder of its own never end up in a later frame's results.
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    /// Nanoseconds per timestamp tick
+    period: f32,
+    /// Passes timed in the frame being recorded
+    labels: Vec<String>,
+    /// Whether a frame is being recorded, see `begin_frame`
+    recording: bool,
+    /// Passes of the frame being read back, and whether mapping succeeded
+    /// once it has finished
+    pending: Option<(Vec<String>, Arc<OnceLock<bool>>)>,
+    pub last_timings: Option<FrameTimings>,
+}
+
+impl GpuTimer {
+    /// `None` unless `device` was created with `Features::TIMESTAMP_QUERY`
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let query_count = 2 * MAX_TIMED_PASSES;
+        let size = query_count as u64 * wgpu::QUERY_SIZE as u64;
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Timestamp Query Set"),
+            ty: wgpu::QueryType::Timestamp,
+            count: query_count,
+        });
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp Resolve Buffer"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp Readback Buffer"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            period: queue.get_timestamp_period(),
+            labels: Vec::new(),
+            recording: false,
+            pending: None,
+            last_timings: None,
+        })
+    }
+
+    /// Start timing the passes of a frame, whose encoder will be passed to
+    /// `resolve`
+    pub fn begin_frame(&mut self) {
+        self.labels.clear();
+        self.recording = true;
+    }
+
+    /// Timestamp writes for the next pass, labelled `label` in the results,
+    /// or `None` if this frame can't be timed or no frame is being recorded
+    pub fn pass_writes(&mut self, label: &str) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        if !self.recording || self.pending.is_some() || self.labels.len() as u32 >= MAX_TIMED_PASSES
+        {
+            return None;
+        }
+        let index = 2 * self.labels.len() as u32;
+        self.labels.push(label.to_string());
+        Some(wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    /// Copy this frame's timestamps to the readback buffer. Call once all
+    /// timed passes are recorded in `encoder`.
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.labels.is_empty() {
+            return;
+        }
+        let query_count = 2 * self.labels.len() as u32;
+        encoder.resolve_query_set(&self.query_set, 0..query_count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            query_count as u64 * wgpu::QUERY_SIZE as u64,
+        );
+    }
+
+    /// Start reading back the resolved timestamps. Call after submitting the
+    /// encoder passed to `resolve`.
+    pub fn begin_readback(&mut self) {
+        self.recording = false;
+        if self.labels.is_empty() {
+            return;
+        }
+        let size = 2 * self.labels.len() as u64 * wgpu::QUERY_SIZE as u64;
+        let mapped = Arc::new(OnceLock::new());
+        let result = mapped.clone();
+        self.readback_buffer
+            .slice(..size)
+            .map_async(wgpu::MapMode::Read, move |status| {
+                let _ = result.set(status.is_ok());
+            });
+        self.pending = Some((std::mem::take(&mut self.labels), mapped));
+    }
+
+    /// Pick up a finished readback into `last_timings`, without waiting
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        let Some((_, mapped)) = &self.pending else {
+            return;
+        };
+        let _ = device.poll(wgpu::PollType::Poll);
+        let Some(&mapped) = mapped.get() else {
+            return;
+        };
+        let Some((labels, _)) = self.pending.take() else {
+            return;
+        };
+        if !mapped {
+            log::warn!("Failed to map timestamp readback buffer");
+            return;
+        }
+        let size = 2 * labels.len() as u64 * wgpu::QUERY_SIZE as u64;
+        let passes = {
+            let data = self.readback_buffer.slice(..size).get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+            labels
+                .into_iter()
+                .zip(timestamps.chunks_exact(2))
+                .map(|(label, pair)| PassTiming {
+                    label,
+                    micros: pair[1].saturating_sub(pair[0]) as f64 * self.period as f64 / 1000.0,
+                })
+                .collect()
+        };
+        self.readback_buffer.unmap();
+        self.last_timings = Some(FrameTimings { passes });
+    }
+}